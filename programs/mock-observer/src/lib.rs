@@ -0,0 +1,48 @@
+#![allow(clippy::result_large_err)] // anchor's Error is big, and every instruction returns it
+#![allow(unexpected_cfgs)] // anchor 0.28's macros check cfgs that newer compilers don't know about
+
+use anchor_lang::prelude::*;
+
+declare_id!("9bgx71ZUPe7ZLoAQJ3uhRwWZHYpdqkZ2BUEJHfYUSSiC");
+
+// stands in for a third-party program registered as a GameObserver, it only counts the callbacks it gets
+#[program]
+pub mod mock_observer {
+    use super::*;
+
+    pub fn initialize(_ctx: Context<Initialize>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn on_game_complete(ctx: Context<OnGameComplete>, game: Pubkey, winner: Option<Pubkey>) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.calls += 1;
+        counter.last_game = game;
+        counter.last_winner = winner;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = payer, space = 8 + 8 + 32 + 33)]
+    pub counter: Account<'info, Counter>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OnGameComplete<'info> {
+    /// CHECK: the finished game, only passed along for reference
+    pub game: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub counter: Account<'info, Counter>,
+}
+
+#[account]
+pub struct Counter {
+    pub calls: u64,
+    pub last_game: Pubkey,
+    pub last_winner: Option<Pubkey>,
+}
@@ -1,24 +1,414 @@
+#![allow(clippy::result_large_err)] // anchor's Error is big, and every instruction returns it
+#![allow(unexpected_cfgs)] // anchor 0.28's macros check cfgs that newer compilers don't know about
+#![allow(non_local_definitions)] // emitted by num-derive 0.3's FromPrimitive/ToPrimitive
+
 use anchor_lang::prelude::*; // imports all items from the 'prelude' module of the 'anchor_lang' crate
+use anchor_lang::system_program; // CPI helpers for the System Program, used to move lamports out of PDAs
+use anchor_lang::solana_program::instruction::Instruction; // raw instructions, for CPIs into programs we don't have a crate for
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::hash::hashv; // sha256 over several byte slices, used for deterministic random picks
 use num_derive::*; // this crate provides procedural macros to derive numeric traits in Rust like FromPrimitive and ToPrimitive
 use num_traits::*; // this crate provides a collection of numeric traits that describe properties of primitive numeric types
 
+pub mod utils; // helpers shared between instructions that aren't tied to a single account type
+
 use crate::program::TicTacToeAnchor; // is a path pointing to the 'TicTacToeAchor' item inside the program module of the current state
 
 
+pub const STARTING_ELO: i32 = 1200; // rating of a player who hasn't played any rated games
+pub const SLOTS_PER_WEEK: u64 = 7 * 24 * 60 * 60 * 5 / 2; // at 400ms per slot
+pub const CURRENT_GAME_VERSION: u8 = 2; // bump whenever the Game layout changes
+
 declare_id!("BwAT2NVQuxS4wuvzSd4MjPUbxMZm4yv791C7E62yYJUp"); // this macro defines the unique program id of a given solana program. Anchor provides a local development environment where it automatically handles the program ID for you, so you don't have to worry about it.
 
 #[program] // this anchor attribute is a procedural macro that denotes the program's main entry point. similar to main function. Under this attribute u define fn's that represent the different instructions or operations that our Solana program can handle.
 pub mod tic_tac_toe_anchor { // modules in Rust are used to organize code into namespaces
     use super::*; // brings all public items from the parent module into scope
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(_ctx: Context<Initialize>) -> Result<()> {
         Ok(())
     } // initialize fn is an instruction handler for a solana program written using anchor. 
     // ctx is a struct that contains the accounts and client information involved in the transaction. 'Context' struct is a generic type provided by anchor, and the initialize type inside the brackets is defined elsewhere in the program. This initialize type represents the specific accounts that the 'initialize' instruction expects.
     // Result is a function that return a result type - success (Ok) or failure (Err). If there was an error, the funtion will return an 'Err' variant that contains info about what went wrong
     // Ok(()) - this is the body of the function. It simply returns 'Ok(())' indicating that this function always succeeds. 
+
+    pub fn setup_game(ctx: Context<SetupGame>, player_two: Pubkey, params: GameSetupParams, wager: u64) -> Result<()> {
+        Game::setup_game(ctx, player_two, params, wager)
+    }
+
+    pub fn play(ctx: Context<Play>, tile: Tile, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        require!(game.players[1] != Pubkey::default(), TicTacToeError::OpponentMissing); // an open game waits for join_open_game
+        game.use_nonce(expected_nonce)?;
+        let clock = Clock::get()?;
+        game.charge_move_time(clock.unix_timestamp, &ctx.accounts.config.settings)?; // before the move, while it's still this player's turn
+        let turn = game.turn;
+        game.play(&tile)?;
+        game.last_move_slot = clock.slot;
+        emit!(MovePlayed {
+            game: game.key(),
+            player: ctx.accounts.player.key(),
+            row: tile.row,
+            column: tile.column,
+            turn,
+            last_move_tile: game.last_move_tile,
+        });
+        if !game.is_active() {
+            emit!(GameEnded { game: game.key(), state: game.state.clone(), final_turn: game.turn });
+        }
+        Ok(())
+    } // the signer is checked against the current player by the Play accounts struct
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        ProgramConfig::initialize_config(ctx)
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, settings: ConfigSettings) -> Result<()> {
+        ctx.accounts.config.settings = settings;
+        Ok(())
+    }
+
+    pub fn deactivate_player(ctx: Context<DeactivatePlayer>, player: Pubkey, reason_hash: [u8; 32]) -> Result<()> {
+        BannedPlayer::deactivate_player(ctx, player, reason_hash)
+    }
+
+    pub fn reinstate_player(_ctx: Context<ReinstatePlayer>) -> Result<()> {
+        Ok(()) // the ban account is closed by the `close` constraint on ReinstatePlayer
+    }
+
+    pub fn initiate_withdrawal(ctx: Context<InitiateWithdrawal>, amount: u64, destination: Pubkey) -> Result<()> {
+        PendingWithdrawal::initiate_withdrawal(ctx, amount, destination)
+    }
+
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        PendingWithdrawal::execute_withdrawal(ctx)
+    }
+
+    pub fn cancel_withdrawal(_ctx: Context<CancelWithdrawal>) -> Result<()> {
+        Ok(()) // the pending withdrawal is closed by the `close` constraint on CancelWithdrawal
+    }
+
+    pub fn join_open_game(ctx: Context<JoinOpenGame>, expected_nonce: u64) -> Result<()> {
+        Game::join_open_game(ctx, expected_nonce)
+    }
+
+    pub fn cancel_game(ctx: Context<CancelGame>) -> Result<()> {
+        Game::cancel_game(ctx)
+    }
+
+    pub fn settle(ctx: Context<Settle>) -> Result<()> {
+        Game::settle(ctx)
+    }
+
+    pub fn garbage_collect_game(ctx: Context<GarbageCollectGame>) -> Result<()> {
+        Game::garbage_collect_game(ctx)
+    }
+
+    pub fn resolve_disputed_game(
+        ctx: Context<ResolveDisputedGame>,
+        winner: Option<Pubkey>,
+        expected_nonce: u64,
+    ) -> Result<()> {
+        Game::resolve_disputed_game(ctx, winner, expected_nonce)
+    }
+
+    pub fn swap_signs(ctx: Context<SwapSigns>, expected_nonce: u64) -> Result<()> {
+        Game::swap_signs(ctx, expected_nonce)
+    }
+
+    pub fn resign(ctx: Context<Resign>, expected_nonce: u64) -> Result<()> {
+        Game::resign(ctx, expected_nonce)
+    }
+
+    pub fn claim_timeout(ctx: Context<ClaimTimeout>, expected_nonce: u64) -> Result<()> {
+        Game::claim_timeout(ctx, expected_nonce)
+    }
+
+    pub fn rematch(ctx: Context<Rematch>) -> Result<()> {
+        Game::rematch(ctx)
+    }
+
+    pub fn offer_draw(ctx: Context<OfferDraw>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        game.use_nonce(expected_nonce)?;
+        game.offer_draw(&ctx.accounts.player.key())
+    }
+
+    pub fn accept_draw(ctx: Context<AcceptDraw>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        game.use_nonce(expected_nonce)?;
+        game.accept_draw(&ctx.accounts.player.key())?;
+        emit!(GameEnded { game: game.key(), state: game.state.clone(), final_turn: game.turn });
+        Ok(())
+    }
+
+    pub fn decline_draw(ctx: Context<DeclineDraw>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        game.use_nonce(expected_nonce)?;
+        game.decline_draw(&ctx.accounts.player.key())
+    }
+
+    pub fn double_check_win_condition(ctx: Context<DoubleCheckWinCondition>) -> Result<()> {
+        Game::double_check_win_condition(ctx)
+    }
+
+    pub fn audit_game_account(ctx: Context<AuditGameAccount>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let computed = game.cell_count() - game.legal_moves_count();
+        if computed != game.play_count {
+            emit!(PlayCountMismatch { game: game.key(), stored: game.play_count, computed });
+        }
+        Ok(())
+    }
+
+    pub fn verify_account_layout(ctx: Context<VerifyAccountLayout>) -> Result<()> {
+        Game::verify_account_layout(ctx)
+    }
+
+    pub fn analyze_threats(ctx: Context<AnalyzeThreats>) -> Result<()> {
+        Game::analyze_threats(ctx)
+    }
+
+    pub fn calculate_expected_outcome(ctx: Context<CalculateExpectedOutcome>) -> Result<()> {
+        ctx.accounts.rate_limit.record_call(Clock::get()?.slot, &ctx.accounts.config.settings)?;
+        let game = &ctx.accounts.game;
+        require!(game.is_active(), TicTacToeError::GameAlreadyOver);
+        let outcome = game.expected_outcome().ok_or(TicTacToeError::PositionTooOpen)?;
+        emit!(ExpectedOutcome { game: game.key(), outcome });
+        Ok(())
+    }
+
+    pub fn generate_game_seed(_ctx: Context<GenerateGameSeed>, seed: [u8; 32]) -> Result<()> {
+        let (moves, game) = Game::random_game(&seed)?;
+        emit!(SeedResult {
+            seed,
+            x_wins: matches!(game.state, GameState::Won { .. }) && moves.len() % 2 == 1, // X makes the odd moves
+            final_state_tag: game.state.tag(),
+            moves,
+        });
+        Ok(())
+    }
+
+    pub fn initialize_global_stats(_ctx: Context<InitializeGlobalStats>) -> Result<()> {
+        Ok(()) // all counters start at zero
+    }
+
+    pub fn initialize_lobby(_ctx: Context<InitializeLobby>) -> Result<()> {
+        Ok(()) // the lobby starts out empty
+    }
+
+    pub fn prune_lobby(ctx: Context<PruneLobby>) -> Result<()> {
+        GameLobby::prune_lobby(ctx)
+    }
+
+    pub fn initialize_rate_limit(ctx: Context<InitializeRateLimit>, instruction_id: u8) -> Result<()> {
+        ctx.accounts.rate_limit.instruction_id = instruction_id;
+        Ok(())
+    }
+
+    pub fn submit_survey(
+        ctx: Context<SubmitSurvey>,
+        enjoyment: u8,
+        fairness: u8,
+        would_play_again: bool,
+    ) -> Result<()> {
+        PostGameSurvey::submit_survey(ctx, enjoyment, fairness, would_play_again)
+    }
+
+    pub fn get_survey_averages(ctx: Context<GetSurveyAverages>) -> Result<()> {
+        let stats = &ctx.accounts.stats;
+        let average_x100 = |sum: u64| (sum * 100).checked_div(stats.survey_count).unwrap_or(0);
+        emit!(SurveyAverages {
+            avg_enjoyment_x100: average_x100(stats.enjoyment_sum),
+            avg_fairness_x100: average_x100(stats.fairness_sum),
+            count: stats.survey_count,
+        });
+        Ok(())
+    }
+
+    pub fn get_daily_leaderboard(ctx: Context<GetDailyLeaderboard>) -> Result<()> {
+        let leaderboard = &ctx.accounts.leaderboard;
+        emit!(DailyLeaderboardEntries { date: leaderboard.date, entries: leaderboard.entries.clone() });
+        Ok(())
+    }
+
+    pub fn create_daily_leaderboard(ctx: Context<CreateDailyLeaderboard>, date: u32) -> Result<()> {
+        ctx.accounts.leaderboard.date = date;
+        Ok(())
+    } // permissionless, the challenge itself follows from the date
+
+    pub fn submit_daily_solution(ctx: Context<SubmitDailySolution>, tile: Tile) -> Result<()> {
+        DailyLeaderboard::submit_daily_solution(ctx, tile)
+    }
+
+    pub fn create_history(ctx: Context<CreateHistory>) -> Result<()> {
+        GameHistory::create_history(ctx)
+    }
+
+    pub fn record_move(ctx: Context<RecordMove>, tile: Tile) -> Result<()> {
+        GameHistory::record_move(ctx, tile)
+    }
+
+    pub fn reconstruct_board(ctx: Context<ReconstructBoard>) -> Result<()> {
+        GameHistory::reconstruct_board(ctx)
+    }
+
+    pub fn match_replay_to_game(ctx: Context<MatchReplayToGame>) -> Result<()> {
+        GameHistory::match_replay_to_game(ctx)
+    }
+
+    pub fn register_game_observer(ctx: Context<RegisterGameObserver>, program_id: Pubkey) -> Result<()> {
+        GameObserver::register_game_observer(ctx, program_id)
+    }
+
+    pub fn unregister_game_observer(ctx: Context<UnregisterGameObserver>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.registered_by.key(), ctx.remaining_accounts)?;
+        ctx.accounts.game.observer_count -= 1;
+        Ok(()) // the observer account is closed by the `close` constraint on UnregisterGameObserver
+    }
+
+    pub fn finalize_game<'info>(ctx: Context<'_, '_, '_, 'info, FinalizeGame<'info>>) -> Result<()> {
+        Game::finalize_game(ctx)
+    }
+
+    pub fn proof_of_play(ctx: Context<ProofOfPlay>, commitment: [u8; 32]) -> Result<()> {
+        PlayProof::proof_of_play(ctx, commitment)
+    }
+
+    pub fn create_room(
+        ctx: Context<CreateRoom>,
+        room_id: u8,
+        name: [u8; 32],
+        config: GameRoomConfig,
+        min_elo: i32,
+        elo_cap: i32,
+        rating_decay_rate: u8,
+    ) -> Result<()> {
+        GameRoom::create_room(ctx, room_id, name, config, min_elo, elo_cap, rating_decay_rate)
+    }
+
+    pub fn apply_rating_decay<'info>(ctx: Context<'_, '_, '_, 'info, ApplyRatingDecay<'info>>) -> Result<()> {
+        GameRoom::apply_rating_decay(ctx)
+    }
+
+    pub fn join_room(ctx: Context<JoinRoom>) -> Result<()> {
+        GameRoom::join_room(ctx)
+    }
+
+    pub fn set_featured_game(ctx: Context<SetFeaturedGame>) -> Result<()> {
+        GameRoom::set_featured_game(ctx)
+    }
+
+    pub fn invite_to_game(ctx: Context<InviteToGame>, to: Pubkey, game_config: GameSetupParams) -> Result<()> {
+        GameInvitation::invite_to_game(ctx, to, game_config)
+    }
+
+    pub fn accept_invitation(ctx: Context<AcceptInvitation>) -> Result<()> {
+        GameInvitation::accept_invitation(ctx)
+    }
+
+    pub fn decline_invitation(ctx: Context<DeclineInvitation>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.to.key(), ctx.remaining_accounts)?;
+        Ok(()) // the invitation is closed by the `close` constraint on DeclineInvitation
+    }
+
+    pub fn create_profile(ctx: Context<CreateProfile>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let profile = &mut ctx.accounts.profile;
+        profile.owner = ctx.accounts.player.key();
+        profile.elo_rating = STARTING_ELO;
+        profile.seasonal_rank = SeasonalRank::Unranked;
+        Ok(()) // all counters start at zero
+    }
+
+    pub fn record_result(ctx: Context<RecordResult>) -> Result<()> {
+        PlayerProfile::record_result(ctx)
+    }
+
+    pub fn add_friend(ctx: Context<AddFriend>, friend: Pubkey) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.owner.key(), ctx.remaining_accounts)?;
+        let friends = &mut ctx.accounts.friends;
+        friends.owner = ctx.accounts.owner.key(); // the list may have just been created
+        friends.add(friend)
+    }
+
+    pub fn remove_friend(ctx: Context<RemoveFriend>, friend: Pubkey) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.owner.key(), ctx.remaining_accounts)?;
+        ctx.accounts.friends.remove(&friend)
+    }
+
+    pub fn get_mutual_friends(ctx: Context<GetMutualFriends>, other: Pubkey) -> Result<()> {
+        emit!(MutualFriends {
+            owner: ctx.accounts.friends.owner,
+            other,
+            friends: ctx.accounts.friends.mutual(&ctx.accounts.other_friends),
+        });
+        Ok(())
+    }
+
+    pub fn create_session(ctx: Context<CreateSession>, tiebreak_rule: TiebreakRule) -> Result<()> {
+        GameSession::create_session(ctx, tiebreak_rule)
+    }
+
+    pub fn play_session(ctx: Context<PlaySession>, tile: Tile, expected_nonce: u64) -> Result<()> {
+        GameSession::play_session(ctx, tile, expected_nonce)
+    }
+
+    pub fn advance_session(ctx: Context<AdvanceSession>) -> Result<()> {
+        GameSession::advance_session(ctx)
+    }
+
+    pub fn finalize_series(ctx: Context<FinalizeSeries>) -> Result<()> {
+        GameSession::finalize_series(ctx)
+    }
+
+    pub fn close_session(ctx: Context<CloseSession>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        Ok(()) // the session is closed by the `close` constraint on CloseSession
+    }
+
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        _nonce: u64,
+        capacity: u8,
+        prize_shares: Vec<u16>,
+        ranked: bool,
+    ) -> Result<()> {
+        Tournament::create_tournament(ctx, capacity, prize_shares, ranked)
+    }
+
+    pub fn register_player(ctx: Context<RegisterPlayer>) -> Result<()> {
+        Tournament::register_player(ctx)
+    }
+
+    pub fn start_tournament<'info>(ctx: Context<'_, '_, '_, 'info, StartTournament<'info>>) -> Result<()> {
+        Tournament::start_tournament(ctx)
+    }
+
+    pub fn advance_winner<'info>(ctx: Context<'_, '_, '_, 'info, AdvanceWinner<'info>>) -> Result<()> {
+        Tournament::advance_winner(ctx)
+    }
+
+    pub fn verify_tournament_bracket(ctx: Context<VerifyTournamentBracket>) -> Result<()> {
+        Tournament::verify_tournament_bracket(ctx)
+    }
+
+    pub fn compute_prize_distribution(ctx: Context<ComputePrizeDistribution>) -> Result<()> {
+        PrizeDistribution::compute_prize_distribution(ctx)
+    }
+
+    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+        PrizeDistribution::claim_prize(ctx)
+    }
 }
 
+#[derive(Accounts)]
+pub struct Initialize {}
+
 #[derive(Accounts)] // this attribute defines a struct that represents the accounts a given instruction expects
 // the derive keyword in Rust is used to automatically create code based on the data type definitions. it is used in conjunction with traits to add default implementations for those traits.
 // The accounts trait in Anchor provides functionalities for parsing and validating solana accounts, which are passed to the instruction.
@@ -27,163 +417,4376 @@ pub struct SetupGame<'info> {
     pub game: Account<'info, Game>,
     #[account(mut)]
     pub player_one: Signer<'info>,
+    #[account(mut)]
+    pub room: Option<Account<'info, GameRoom>>, // set when the game is played inside a room
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    #[account(seeds = [b"friends", player_one.key().as_ref()], bump)]
+    pub friends: Option<Account<'info, FriendsList>>, // player_one's friends, needed for friends-only games
+    /// CHECK: player_one's PlayerProfile PDA, it doesn't have to exist - like the ban PDA it's required so the abandon check can't be skipped
+    #[account(seeds = [b"profile", player_one.key().as_ref()], bump)]
+    pub profile: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"vault", game.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>, // holds both wagers until the game is settled
+    #[account(mut, seeds = [b"lobby"], bump)]
+    pub lobby: Account<'info, GameLobby>, // open games are listed here until someone joins
     pub system_program: Program<'info, System>
 } // it can be used to initialize a new Game account
 // Game field will contain the address of the newly created account
+// the ban PDA of player_one has to be passed as a remaining account so setup_game can check the player isn't banned
 
+#[derive(Accounts)]
+pub struct Play<'info> {
+    #[account(mut, constraint = game.current_player()? == player.key() @ TicTacToeError::NotPlayersTurn)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>, // move time limits for the time banks
+} // the ban PDA of the player follows in remaining_accounts
 
-#[account] // an attribute macro that provides information about how to use a specific struct as an account in the program. This means instances of Game will be stored in Solana accounts. And every new game requires a new account.
-pub struct Game { // represents a game state in a solana program
-    players: [Pubkey; 2], // holds public keys of the 2 players involved in the game
-    turn: u8, // represents the current player's turn - either 0 or 1
-    board: [[Option<Sign>; 3]; 3], // a 3x3 matrix that represents game board. Each cell on the board holds an Option<Sign> - either holds a sign (X or O) or can be empty (None)
-    state: GameState, // represents overall state of the game. the exact values it can take on is mentioned below in GameState enum
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(init, payer = authority, space = 8 + ProgramConfig::MAXIMUM_SIZE, seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, TicTacToeAnchor>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+} // only the upgrade authority of the program can create the config, so nobody can front-run the deploy and take over admin rights
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    pub authority: Signer<'info>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)] 
-// First 2 tells Rust to automatically generate code for serializing and deserializing 'GameState' instances. This is because data needs to be serialized to be stored in a Solana account, and then deserialized to be read and used in the program
-// Clone tells Rust to generate a '.clone()' method for 'GameState' instances. This method will create a copy of 'GameState'
-// PartialEq allows GameState instances to be compared for equality using '==' operator
-// Eq - This trait indicates that all comparisions of 'GameState' instances are reflexive, symmetric and transitive, which are the conditions needed to define a today equivalence relation.
-pub enum GameState { // represents the possible states that a game could be in
-    Active,
-    Tie,
-    Won { winner: Pubkey },
+#[derive(Accounts)]
+pub struct InitiateWithdrawal<'info> {
+    #[account(init, payer = authority, space = 8 + PendingWithdrawal::MAXIMUM_SIZE, seeds = [b"withdrawal"], bump)]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+} // only one withdrawal can be pending at a time
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(mut, close = authority, seeds = [b"withdrawal"], bump, has_one = destination)]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+    /// CHECK: has to match the destination fixed when the withdrawal was initiated
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(
-    AnchorSerialize,
-    AnchorDeserialize,
-    FromPrimitive, // allows converting instances into primitive types
-    ToPrimitive, // vice-versa of above
-    Copy, // this trait means that this type will be 'copy'able
-    Clone,
-    PartialEq,
-    Eq
-)]
-pub enum Sign {
-    X,
-    O,
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(mut, close = authority, seeds = [b"withdrawal"], bump)]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct Tile {
-    row: u8,
-    column: u8,
-} // this struct is also stored on Solana
+#[derive(Accounts)]
+pub struct JoinOpenGame<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub player: Signer<'info>, // pays the same wager as player_one
+    #[account(mut, seeds = [b"vault", game.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, seeds = [b"lobby"], bump)]
+    pub lobby: Account<'info, GameLobby>,
+    #[account(constraint = game.room == Some(room.key()) @ TicTacToeError::RoomAccountMismatch)]
+    pub room: Option<Account<'info, GameRoom>>, // required for games set up in a room
+    /// CHECK: the joiner's PlayerProfile PDA, it doesn't have to exist - without a profile the joiner rates as STARTING_ELO
+    #[account(seeds = [b"profile", player.key().as_ref()], bump)]
+    pub profile: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+} // claims the open second seat of a game set up without a player_two
 
-#[error_code] // macro provided by anchor that automates the process of mapping these custom errors to error codes. This attribute will create an 'impl ErrorCode for TicTacToeError' block where each variant is assigned a unique error code
-pub enum TicTacToeError {
-    TileOutOfBounds,
-    TileAlreadySet,
-    GameAlreadyOver,
-    NotPlayersTurn,
-    GameAlreadyStarted,
+#[derive(Accounts)]
+pub struct SwapSigns<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>,
 }
 
-impl Game { // to define methods on the struct Game
-    pub const MAXIMUM_SIZE: usize = (32 * 2) + 1 + (9 * (1 + 1)) + (32 + 1);
+#[derive(Accounts)]
+pub struct Resign<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>, // either player, on their turn or not
+}
 
-    pub fn setup_game(ctx: Context<SetupGame>, player_two: Pubkey) -> Result<()> {
-        ctx.accounts.game.start([ctx.accounts.player_one.key(), player_two])
-    }
+#[derive(Accounts)]
+pub struct ClaimTimeout<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>, // either player, the win goes to whoever isn't on the move
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>, // move time limit for games with a time bank
+}
 
-    pub fn start(&mut self, players: [Pubkey; 2]) -> Result<()> {
-        require_eq!(self.turn, 0, TicTacToeError::GameAlreadyStarted); // checks that game has been started yet
-        self.players = players; // sets the 'players' field to the 2 players who will be playing the game
-        self.turn = 1; // indicates that it is the first player's turn
-        Ok(()) // returns a success value
-    } 
-    /// starts a new game and sets up initial state require_eq! is a rust macro that ensure that two values are equal.
-    /// In this case, the macro is checking to make sure that the turn field is equal to 0. Otherwise return an error.
-    /// 
+#[derive(Accounts)]
+pub struct OfferDraw<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>, // either player, on their turn or not
+}
 
-    pub fn is_active(&self) -> bool {
-        self.state == GameState::Active
-    }
-    /// checks if a game is active
-    /// self is a reference to the Game struct that the function is being called on
+#[derive(Accounts)]
+pub struct AcceptDraw<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>, // the opponent of whoever offered the draw
+}
 
-    fn current_player_index(&self) -> usize {
-        ((self.turn - 1) % 2) as usize
-    } // returns the index of the current player to decide whose turn it is
+#[derive(Accounts)]
+pub struct DeclineDraw<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub player: Signer<'info>, // either player, so the offer can also be taken back
+}
 
-    pub fn current_player(&self) -> Pubkey {
-        self.players[self.current_player_index()]
-    } // return public key of the current player
+#[derive(Accounts)]
+pub struct Rematch<'info> {
+    pub old_game: Account<'info, Game>,
+    #[account(init, payer = initiator, space = 8 + Game::MAXIMUM_SIZE)]
+    pub new_game: Account<'info, Game>,
+    #[account(mut)]
+    pub initiator: Signer<'info>, // either player of old_game
+    #[account(mut)]
+    pub opponent: Option<Signer<'info>>, // the other player, only needed to put their wager in again
+    #[account(mut, seeds = [b"vault", new_game.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    /// CHECK: the initiator's PlayerProfile PDA, it doesn't have to exist - same as in SetupGame
+    #[account(seeds = [b"profile", initiator.key().as_ref()], bump)]
+    pub profile: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+} // the ban PDAs of both players follow in remaining_accounts
 
-    pub fn play(&mut self, tile: &Tile) -> Result<()> {
-        require!(self.is_active(), TicTacToeError::GameAlreadyOver);
+#[derive(Accounts)]
+pub struct DoubleCheckWinCondition<'info> {
+    pub game: Account<'info, Game>,
+} // read-only, anyone can audit any game
 
-        match tile {
-            tile @ Tile{
-                row: 0..=2,
-                column: 0..=2,
-            } => match self.board[tile.row as usize][tile.column as usize] {
-                Some(_) => return Err(TicTacToeError::TileAlreadySet.into()),
-                None => {
-                    self.board[tile.row as usize][tile.column as usize] = 
-                        Some(Sign::from_usize(self.current_player_index()).unwrap());
-                }
-            },
-            _ => return Err(TicTacToeError::TileOutOfBounds.into()),
-        }
+#[derive(Accounts)]
+pub struct AuditGameAccount<'info> {
+    pub game: Account<'info, Game>,
+} // read-only, checks the counters against the board
 
-        self.update_state();
+#[derive(Accounts)]
+pub struct VerifyAccountLayout<'info> {
+    /// CHECK: deliberately not deserialized, the instruction reports whether it still can be
+    #[account(owner = crate::ID)]
+    pub game: UncheckedAccount<'info>,
+} // read-only, run across all games before an upgrade that changes the Game layout
 
-        if GameState::Active == self.state {
-            self.turn += 1;
-        }
+#[derive(Accounts)]
+pub struct MatchReplayToGame<'info> {
+    pub history_a: Account<'info, GameHistory>,
+    #[account(constraint = history_b.game == history_a.game @ TicTacToeError::HistoryGameMismatch)]
+    pub history_b: Account<'info, GameHistory>,
+} // read-only, compares two recordings of the same game
 
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct GenerateGameSeed {} // pure computation, touches no accounts
 
-    fn is_winning_trio(&self, trio: [(usize, usize); 3]) -> bool {
-        let [first, second, third] = trio;
-        self.board[first.0][first.1].is_some() 
-            && self.board[first.0][first.1] == self.board[second.0][second.1]
-            && self.board[first.0][first.1] == self.board[third.0][third.1]
-    }
+#[derive(Accounts)]
+pub struct InitializeGlobalStats<'info> {
+    #[account(init, payer = payer, space = 8 + GlobalStats::MAXIMUM_SIZE, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-    fn update_state(&mut self) {
-        for i in 0..=2 {
-            // three of the same in one row
-            if self.is_winning_trio([(i,0), (i,1), (i,2)]) {
-                self.state = GameState::Won {
-                    winner: self.current_player(),
-                };
-                return;
-            }
-            // three of the same in one column
-            if self.is_winning_trio([(0,i), (1,i), (2,i)]) {
-                self.state = GameState::Won {
-                    winner: self.current_player(),
-                };
-                return;
-            } 
-        }
-        // three of the same in one diagonal
-        if self.is_winning_trio([(0,0), (1,1), (2,2)])
-            || self.is_winning_trio([(0,2), (1,1), (2,0)]) {
-                self.state = GameState::Won {
-                    winner: self.current_player(),
-                };
-                return;
-            }
-        
-        // reaching this code means the game has not been won,
-        // so if there are unfilled tiles left, it's still active
-        for row in 0..=2 {
-            for column in 0..=2 {
-                if self.board[row][column].is_none() {
-                    return;
-                }
-            }
-        }
+#[derive(Accounts)]
+pub struct InitializeLobby<'info> {
+    #[account(init, payer = payer, space = 8 + GameLobby::MAXIMUM_SIZE, seeds = [b"lobby"], bump)]
+    pub lobby: Account<'info, GameLobby>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        // game has not been won 
-        // game has no more free tiles
-        // -> game ends in a tie
-        self.state = GameState::Tie;
-    }
-}
\ No newline at end of file
+#[derive(Accounts)]
+pub struct PruneLobby<'info> {
+    #[account(mut, seeds = [b"lobby"], bump)]
+    pub lobby: Account<'info, GameLobby>,
+} // the listed games to check are passed as remaining accounts
+
+#[derive(Accounts)]
+#[instruction(instruction_id: u8)]
+pub struct InitializeRateLimit<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InstructionRateLimit::MAXIMUM_SIZE,
+        seeds = [b"ratelimit".as_ref(), &[instruction_id]],
+        bump
+    )]
+    pub rate_limit: Account<'info, InstructionRateLimit>,
+    #[account(seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitSurvey<'info> {
+    pub game: Account<'info, Game>,
+    #[account(
+        init,
+        payer = respondent,
+        space = 8 + PostGameSurvey::MAXIMUM_SIZE,
+        seeds = [b"survey", game.key().as_ref(), respondent.key().as_ref()],
+        bump
+    )]
+    pub survey: Account<'info, PostGameSurvey>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    #[account(mut)]
+    pub respondent: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetSurveyAverages<'info> {
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+}
+
+#[derive(Accounts)]
+pub struct GetDailyLeaderboard<'info> {
+    #[account(seeds = [b"daily_lb", &leaderboard.date.to_le_bytes()], bump)]
+    pub leaderboard: Account<'info, DailyLeaderboard>,
+}
+
+#[derive(Accounts)]
+#[instruction(date: u32)]
+pub struct CreateDailyLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DailyLeaderboard::MAXIMUM_SIZE,
+        seeds = [b"daily_lb".as_ref(), date.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, DailyLeaderboard>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitDailySolution<'info> {
+    #[account(mut, seeds = [b"daily_lb", &leaderboard.date.to_le_bytes()], bump)]
+    pub leaderboard: Account<'info, DailyLeaderboard>,
+    #[account(
+        init_if_needed,
+        payer = solver,
+        space = 8 + DailyAttempts::MAXIMUM_SIZE,
+        seeds = [b"daily_attempts".as_ref(), leaderboard.date.to_le_bytes().as_ref(), solver.key().as_ref()],
+        bump
+    )]
+    pub attempts: Account<'info, DailyAttempts>, // wrong answers count too
+    #[account(mut)]
+    pub solver: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateHistory<'info> {
+    pub game: Account<'info, Game>,
+    #[account(
+        init,
+        payer = recorder,
+        space = 8 + GameHistory::MAXIMUM_SIZE,
+        seeds = [b"history", game.key().as_ref(), recorder.key().as_ref()],
+        bump
+    )]
+    pub history: Account<'info, GameHistory>,
+    #[account(mut)]
+    pub recorder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordMove<'info> {
+    #[account(mut, has_one = recorder)]
+    pub history: Account<'info, GameHistory>,
+    pub recorder: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReconstructBoard<'info> {
+    pub game: Account<'info, Game>,
+    #[account(has_one = game)]
+    pub history: Account<'info, GameHistory>,
+} // read-only, anyone can check a game against anyone's recording of it
+
+#[derive(Accounts)]
+pub struct CalculateExpectedOutcome<'info> {
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [b"ratelimit".as_ref(), &[InstructionRateLimit::EXPECTED_OUTCOME]], bump)]
+    pub rate_limit: Account<'info, InstructionRateLimit>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+} // anyone can ask how a game ends with perfect play, only the global call counter is written
+
+#[derive(Accounts)]
+pub struct AnalyzeThreats<'info> {
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [b"ratelimit".as_ref(), &[InstructionRateLimit::ANALYZE_THREATS]], bump)]
+    pub rate_limit: Account<'info, InstructionRateLimit>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+} // reports tactical facts about the position as events, only the global call counter is written
+
+#[derive(Accounts)]
+pub struct CancelGame<'info> {
+    #[account(mut, close = player_one, constraint = game.players[0] == player_one.key() @ TicTacToeError::NotAPlayer)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub player_one: SystemAccount<'info>, // has to sign unless the referee cancels
+    pub referee: Option<Signer<'info>>,
+    #[account(mut, seeds = [b"vault", game.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>, // player_one's wager goes back with the rent
+    #[account(mut, seeds = [b"lobby"], bump)]
+    pub lobby: Account<'info, GameLobby>,
+    #[account(mut, constraint = game.room == Some(room.key()) @ TicTacToeError::RoomAccountMismatch)]
+    pub room: Option<Account<'info, GameRoom>>, // required for games set up in a room
+    pub system_program: Program<'info, System>,
+} // lets player_one take back the rent of an open game nobody joined, or the referee call off any unfinished game
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [b"vault", game.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, address = game.players[0] @ TicTacToeError::NotAPlayer)]
+    pub player_one: SystemAccount<'info>,
+    #[account(mut, address = game.players[1] @ TicTacToeError::NotAPlayer)]
+    pub player_two: SystemAccount<'info>,
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>, // gets the platform fee
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+} // permissionless, pays out the vault of a finished game
+
+#[derive(Accounts)]
+pub struct GarbageCollectGame<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut, constraint = game.room == Some(room.key()) @ TicTacToeError::RoomAccountMismatch)]
+    pub room: Option<Account<'info, GameRoom>>, // required for games set up in a room
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+    #[account(mut, seeds = [b"lobby"], bump)]
+    pub lobby: Account<'info, GameLobby>, // an abandoned open game leaves the lobby too
+    #[account(mut)]
+    pub finder: Signer<'info>,
+    #[account(mut, seeds = [b"vault", game.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>, // a wager at stake is split between the players
+    #[account(mut, address = game.players[0] @ TicTacToeError::NotAPlayer)]
+    pub player_one: SystemAccount<'info>,
+    #[account(mut, address = game.players[1] @ TicTacToeError::NotAPlayer)]
+    pub player_two: Option<SystemAccount<'info>>, // required once someone joined the game
+    pub system_program: Program<'info, System>,
+} // anyone can clean up an abandoned game and keep most of its rent
+
+#[derive(Accounts)]
+pub struct ResolveDisputedGame<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    pub referee: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(program_id: Pubkey)]
+pub struct RegisterGameObserver<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(
+        init,
+        payer = registered_by,
+        space = 8 + GameObserver::MAXIMUM_SIZE,
+        seeds = [b"observer", game.key().as_ref(), program_id.as_ref()],
+        bump
+    )]
+    pub observer: Account<'info, GameObserver>,
+    #[account(mut)]
+    pub registered_by: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnregisterGameObserver<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut, close = registered_by, has_one = game, has_one = registered_by)]
+    pub observer: Account<'info, GameObserver>,
+    #[account(mut)]
+    pub registered_by: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeGame<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut, constraint = game.room == Some(room.key()) @ TicTacToeError::RoomAccountMismatch)]
+    pub room: Option<Account<'info, GameRoom>>, // required for games set up in a room
+    #[account(
+        mut,
+        constraint = game.room == Some(member_one.room) && member_one.player == game.players[0]
+            @ TicTacToeError::RoomAccountMismatch
+    )]
+    pub member_one: Option<Account<'info, RoomMember>>, // counts the game towards the players' room calibration
+    #[account(
+        mut,
+        constraint = game.room == Some(member_two.room) && member_two.player == game.players[1]
+            @ TicTacToeError::RoomAccountMismatch
+    )]
+    pub member_two: Option<Account<'info, RoomMember>>,
+} // every registered observer has to follow in remaining_accounts as (GameObserver PDA, observer program, observer state account)
+
+#[derive(Accounts)]
+pub struct ProofOfPlay<'info> {
+    pub game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + PlayProof::MAXIMUM_SIZE,
+        seeds = [b"proof", game.key().as_ref(), &[game.role_of(&player.key())]],
+        bump
+    )]
+    pub proof: Account<'info, PlayProof>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player: Pubkey)]
+pub struct DeactivatePlayer<'info> {
+    #[account(init, payer = authority, space = 8 + BannedPlayer::MAXIMUM_SIZE, seeds = [b"ban", player.as_ref()], bump)]
+    pub banned_player: Account<'info, BannedPlayer>,
+    #[account(seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(room_id: u8)]
+pub struct CreateRoom<'info> {
+    #[account(init, payer = creator, space = 8 + GameRoom::MAXIMUM_SIZE, seeds = [b"room", creator.key().as_ref(), &[room_id]], bump)]
+    pub room: Account<'info, GameRoom>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyRatingDecay<'info> {
+    #[account(mut)]
+    pub room: Account<'info, GameRoom>,
+} // the RoomMember accounts to decay follow in remaining_accounts, writable
+
+#[derive(Accounts)]
+pub struct JoinRoom<'info> {
+    #[account(mut)]
+    pub room: Account<'info, GameRoom>,
+    #[account(init, payer = player, space = 8 + RoomMember::MAXIMUM_SIZE, seeds = [b"room_member", room.key().as_ref(), player.key().as_ref()], bump)]
+    pub member: Account<'info, RoomMember>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    /// CHECK: the player's PlayerProfile PDA, it doesn't have to exist - same as in JoinOpenGame
+    #[account(seeds = [b"profile", player.key().as_ref()], bump)]
+    pub profile: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeaturedGame<'info> {
+    #[account(mut, has_one = creator)]
+    pub room: Account<'info, GameRoom>,
+    #[account(constraint = game.room == Some(room.key()) @ TicTacToeError::RoomAccountMismatch)]
+    pub game: Account<'info, Game>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(to: Pubkey)]
+pub struct InviteToGame<'info> {
+    #[account(
+        init,
+        payer = from,
+        space = 8 + GameInvitation::MAXIMUM_SIZE,
+        seeds = [b"invite", from.key().as_ref(), to.as_ref()],
+        bump
+    )]
+    pub invitation: Account<'info, GameInvitation>,
+    #[account(mut)]
+    pub from: Signer<'info>,
+    #[account(seeds = [b"friends", from.key().as_ref()], bump)]
+    pub friends: Account<'info, FriendsList>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptInvitation<'info> {
+    #[account(mut, close = from, has_one = from, has_one = to)]
+    pub invitation: Account<'info, GameInvitation>,
+    #[account(init, payer = to, space = 8 + Game::MAXIMUM_SIZE)]
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub from: SystemAccount<'info>, // gets the invitation's rent back
+    #[account(mut)]
+    pub to: Signer<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeclineInvitation<'info> {
+    #[account(mut, close = from, has_one = from, has_one = to)]
+    pub invitation: Account<'info, GameInvitation>,
+    #[account(mut)]
+    pub from: SystemAccount<'info>,
+    pub to: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProfile<'info> {
+    #[account(init, payer = player, space = 8 + PlayerProfile::MAXIMUM_SIZE, seeds = [b"profile", player.key().as_ref()], bump)]
+    pub profile: Account<'info, PlayerProfile>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordResult<'info> {
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    /// CHECK: the PlayerProfile PDA of players[0], it doesn't have to exist - both are required so neither side can
+    /// keep the opponent's rating out of the ELO change
+    #[account(mut, seeds = [b"profile", game.players[0].as_ref()], bump)]
+    pub profile_one: UncheckedAccount<'info>,
+    /// CHECK: the PlayerProfile PDA of players[1], same as profile_one
+    #[account(mut, seeds = [b"profile", game.players[1].as_ref()], bump)]
+    pub profile_two: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>, // rank thresholds
+} // permissionless, each player's result is counted once
+
+#[derive(Accounts)]
+#[instruction(friend: Pubkey)]
+pub struct AddFriend<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + FriendsList::MAXIMUM_SIZE,
+        seeds = [b"friends", owner.key().as_ref()],
+        bump
+    )]
+    pub friends: Account<'info, FriendsList>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"profile", friend.as_ref()], bump)]
+    pub friend_profile: Account<'info, PlayerProfile>, // only players with a profile can be added
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFriend<'info> {
+    #[account(mut, seeds = [b"friends", owner.key().as_ref()], bump, has_one = owner)]
+    pub friends: Account<'info, FriendsList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(other: Pubkey)]
+pub struct GetMutualFriends<'info> {
+    pub friends: Account<'info, FriendsList>,
+    #[account(seeds = [b"friends", other.as_ref()], bump)]
+    pub other_friends: Account<'info, FriendsList>,
+} // read-only
+
+#[derive(Accounts)]
+pub struct CreateSession<'info> {
+    #[account(
+        init,
+        payer = player_one,
+        space = 8 + GameSession::MAXIMUM_SIZE,
+        seeds = [b"session", player_one.key().as_ref(), player_two.key().as_ref()],
+        bump
+    )]
+    pub session: Account<'info, GameSession>,
+    #[account(mut)]
+    pub player_one: Signer<'info>,
+    /// CHECK: only used as the opponent's address
+    pub player_two: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaySession<'info> {
+    #[account(mut)]
+    pub session: Account<'info, GameSession>,
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceSession<'info> {
+    #[account(mut)]
+    pub session: Account<'info, GameSession>,
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSeries<'info> {
+    #[account(mut)]
+    pub session: Account<'info, GameSession>,
+    pub player: Signer<'info>, // either player of the session
+    /// CHECK: player_one's PlayerProfile PDA, it doesn't have to exist - both are required so EloDecider sees both ratings
+    #[account(seeds = [b"profile", session.player_one.as_ref()], bump)]
+    pub profile_one: UncheckedAccount<'info>,
+    /// CHECK: player_two's PlayerProfile PDA, same as profile_one
+    #[account(seeds = [b"profile", session.player_two.as_ref()], bump)]
+    pub profile_two: UncheckedAccount<'info>,
+} // the player's ban PDA follows in remaining_accounts
+
+#[derive(Accounts)]
+pub struct CloseSession<'info> {
+    #[account(
+        mut,
+        close = player_one,
+        has_one = player_one,
+        constraint = player.key() == session.player_one || player.key() == session.player_two @ TicTacToeError::NotAPlayer
+    )]
+    pub session: Account<'info, GameSession>,
+    /// CHECK: receives the rent it paid for the session
+    #[account(mut)]
+    pub player_one: UncheckedAccount<'info>,
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CreateTournament<'info> {
+    #[account(
+        init,
+        payer = organizer,
+        space = 8 + Tournament::MAXIMUM_SIZE,
+        seeds = [b"tournament", organizer.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+} // the nonce lets one organizer run several tournaments
+
+#[derive(Accounts)]
+pub struct RegisterPlayer<'info> {
+    #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    pub player: Signer<'info>,
+    /// CHECK: the player's PlayerProfile PDA, it doesn't have to exist - without one the player rates as a newcomer
+    #[account(seeds = [b"profile", player.key().as_ref()], bump)]
+    pub profile: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>, // rank thresholds for ranked tournaments
+} // the ban PDA of the player follows in remaining_accounts
+
+#[derive(Accounts)]
+pub struct StartTournament<'info> {
+    #[account(mut, has_one = organizer)]
+    pub tournament: Account<'info, Tournament>,
+    #[account(mut)]
+    pub organizer: Signer<'info>, // pays the rent of the round one games
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    pub system_program: Program<'info, System>,
+} // the organizer's ban PDA follows in remaining_accounts, then the round one game PDAs in slot order
+
+#[derive(Accounts)]
+pub struct AdvanceWinner<'info> {
+    #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    #[account(mut)]
+    pub game: Account<'info, Game>, // a finished game of the current round
+    #[account(mut)]
+    pub payer: Signer<'info>, // anyone, pays the rent of the next round's games
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+    /// CHECK: the PlayerProfile PDA of players[0], it doesn't have to exist - the final counts both finalists on theirs
+    #[account(mut, seeds = [b"profile", game.players[0].as_ref()], bump)]
+    pub profile_one: UncheckedAccount<'info>,
+    /// CHECK: the PlayerProfile PDA of players[1], same as profile_one
+    #[account(mut, seeds = [b"profile", game.players[1].as_ref()], bump)]
+    pub profile_two: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+} // the payer's ban PDA follows in remaining_accounts, then, when the game completes its round, the next round's game PDAs in slot order
+
+#[derive(Accounts)]
+pub struct VerifyTournamentBracket<'info> {
+    pub tournament: Account<'info, Tournament>,
+} // read-only, the game of every slot set up so far follows in remaining_accounts in slot order
+
+#[derive(Accounts)]
+pub struct ComputePrizeDistribution<'info> {
+    pub tournament: Account<'info, Tournament>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PrizeDistribution::MAXIMUM_SIZE,
+        seeds = [b"prize_distribution", tournament.key().as_ref()],
+        bump
+    )]
+    pub distribution: Account<'info, PrizeDistribution>,
+    #[account(seeds = [b"prize_pool", tournament.key().as_ref()], bump)]
+    pub prize_pool: SystemAccount<'info>, // funded by plain transfers from the organizer or sponsors
+    #[account(mut)]
+    pub payer: Signer<'info>, // anyone, pays the rent of the distribution
+    pub system_program: Program<'info, System>,
+} // once per finished tournament, lamports sent to the pool later stay there
+
+#[derive(Accounts)]
+pub struct ClaimPrize<'info> {
+    pub tournament: Account<'info, Tournament>,
+    #[account(mut, seeds = [b"prize_distribution", tournament.key().as_ref()], bump, has_one = tournament)]
+    pub distribution: Account<'info, PrizeDistribution>,
+    #[account(mut, seeds = [b"prize_pool", tournament.key().as_ref()], bump)]
+    pub prize_pool: SystemAccount<'info>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReinstatePlayer<'info> {
+    #[account(mut, close = authority, seeds = [b"ban", banned_player.player.as_ref()], bump)]
+    pub banned_player: Account<'info, BannedPlayer>,
+    #[account(seeds = [b"config"], bump, has_one = authority)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+
+#[account] // an attribute macro that provides information about how to use a specific struct as an account in the program. This means instances of Game will be stored in Solana accounts. And every new game requires a new account.
+pub struct Game { // represents a game state in a solana program
+    players: [Pubkey; 2], // holds public keys of the 2 players involved in the game
+    turn: u8, // represents the current player's turn - either 0 or 1
+    board: [Option<Sign>; 25], // the cells row by row, the first board_size * board_size are in use. Each cell on the board holds an Option<Sign> - either holds a sign (X or O) or can be empty (None)
+    board_size: u8, // rows and columns of the board, 3 to 5
+    win_length: u8, // signs in a row, column or diagonal needed to win, 3 up to board_size
+    state: GameState, // represents overall state of the game. the exact values it can take on is mentioned below in GameState enum
+    last_move_tile: Option<Tile>, // the most recently played tile, so frontends can highlight it without a full move history
+    nonce: u64, // bumped by every state-changing instruction so a retried transaction can't be applied twice
+    room: Option<Pubkey>, // the GameRoom this game was set up in, if any
+    invite_expiry: i64, // unix timestamp after which nobody can join an open game anymore
+    minimum_elo_requirement: i32, // lowest rating allowed to join
+    maximum_elo_requirement: Option<i32>, // highest rating allowed to join, if any
+    observer_count: u8, // number of GameObserver PDAs registered for this game
+    finalized: bool, // set by finalize_game once the result has been handed out
+    swap_applied: bool, // player_two used the pie rule, so the opener now plays O and replays have to swap the first move
+    ended_at: i64, // unix timestamp of finalize_game, 0 while the game hasn't been finalized
+    referee: Option<Pubkey>, // neutral third party who may resolve or call off the game
+    dispute_resolution_timeout: i64, // unix timestamp after which the referee can't act anymore
+    time_bank_p1: u32, // seconds of saved up move time of players[0]
+    time_bank_p2: u32, // seconds of saved up move time of players[1]
+    last_move_at: i64, // unix timestamp of setup or the latest move, whichever came last
+    created_via: CreatedVia, // which instruction created the game, for analytics
+    play_count: u8, // moves played so far, kept next to turn so the two can be checked against the board
+    version: u8, // layout version the account was created with, see CURRENT_GAME_VERSION
+    wager: u64, // lamports each player put into the [b"vault", game] PDA, 0 for a friendly game
+    wager_settled: bool, // settle has paid out the vault
+    resigned: bool, // the game ended with a resignation, which settles with resign_penalty_bps
+    turn_deadline_slots: u64, // slots a player has for a move before the opponent can claim the win, 0 for no limit
+    last_move_slot: u64, // slot of the latest move, or of the start of the game
+    moves: [MoveRecord; 25], // every move in the order it was played, the first play_count entries are filled
+    timed_out: bool, // the game ended with a claim_timeout, which counts as an abandon for the loser
+    results_recorded: [bool; 2], // record_result has counted the game on the profile of players[0] / players[1]
+    draw_offered_by: Option<Pubkey>, // player waiting for the opponent to accept a draw
+    time_bank_enabled: bool, // set up with a time bank, so running out of move time loses the game
+}
+
+// a coarse compile time guard for Game::MAXIMUM_SIZE. The in-memory layout differs from the borsh encoding (packed
+// Options, padding), so only a sizeable omission trips it - maximum_size_fits_the_largest_game checks the exact size
+const _: () = assert!(std::mem::size_of::<Game>() <= Game::MAXIMUM_SIZE);
+
+#[account]
+pub struct ProgramConfig { // program wide settings, stored at the [b"config"] PDA
+    pub authority: Pubkey, // admin allowed to ban players and change settings
+    pub settings: ConfigSettings,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigSettings { // the tunable part of ProgramConfig, replaced as a whole by update_config
+    pub invite_ttl_secs: i64, // how long an open game waits for a second player
+    pub withdrawal_delay_secs: i64, // how long a treasury withdrawal has to wait before it can be executed
+    pub resign_penalty_bps: u16, // share of a wager pot (after fees) the winner gets when the opponent resigns
+    pub platform_fee_bps: u16, // share of every wager pot that goes to the treasury
+    pub rank_thresholds: [i32; 5], // lowest ELO for Bronze, Silver, Gold, Platinum and Diamond
+    pub max_allowed_abandons: u32, // abandons a player may have before setup_game locks them out
+    pub abandon_recovery_games: u32, // clean games after the last abandon that lift the lockout again
+    pub referee_window_secs: i64, // how long after setup a game's referee may act
+    pub move_time_limit_secs: u32, // time a move may take before it eats into the player's time bank
+    pub max_time_bank_accrual_secs: u32, // most a single quick move can add to the time bank
+    pub gc_grace_period_secs: i64, // inactivity after which anyone may garbage collect a game
+    pub max_calls_per_slot: u32, // program wide cap per slot on each rate limited instruction
+}
+
+#[account]
+pub struct PendingWithdrawal { // a time-locked withdrawal from the [b"treasury"] PDA, stored at [b"withdrawal"]
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub unlocks_at: i64, // execute_withdrawal fails before this unix timestamp
+}
+
+#[account]
+pub struct BannedPlayer { // exists at [b"ban", player] for as long as the player is banned
+    pub player: Pubkey,
+    pub reason_hash: [u8; 32], // hash of the off-chain write-up explaining the ban
+    pub banned_at: i64,
+    pub banned_by: Pubkey,
+}
+
+#[account]
+pub struct GameRoom { // groups games by theme or ruleset so players can find each other, stored at [b"room", creator, room_id]
+    pub name: [u8; 32],
+    pub creator: Pubkey,
+    pub config: GameRoomConfig, // rules every game set up in this room inherits
+    pub member_count: u32,
+    pub active_games: u16,
+    pub elo_cap: i32, // highest rating allowed to play in the room
+    pub min_elo: i32, // lowest rating allowed to play in the room
+    pub min_games_before_ranking: u8, // games a new member plays before showing up on the room's leaderboard
+    pub rating_decay_rate: u8, // percent of their rating inactive members lose per week, 0 for no decay
+    pub last_decay_slot: u64, // slot of the latest apply_rating_decay
+    pub featured_game: Option<Pubkey>, // game the creator highlights in the lobby, cleared once it's finalized
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct GameRoomConfig { // mirrors the SetupGame parameters a room fixes for its games
+    pub board_size: u8,
+    pub win_length: u8,
+    pub custom_rules: u8, // bit flags for rule variants, 0 for standard rules
+}
+
+#[account]
+pub struct RoomMember { // one per player per room, stored at [b"room_member", room, player]
+    pub room: Pubkey,
+    pub player: Pubkey,
+    pub joined_at: i64,
+    pub calibration_remaining: u8, // room games left before the member is ranked
+    pub unranked: bool, // still calibrating, kept off the room leaderboard
+    pub elo_rating: i32, // rating within this room
+    pub last_active_slot: u64, // slot the member joined or last finished a room game
+}
+
+#[account]
+pub struct GameObserver { // an external program notified through CPI when the game is finalized, stored at [b"observer", game, observer]
+    pub observer: Pubkey, // program id called with on_game_complete
+    pub game: Pubkey,
+    pub registered_by: Pubkey, // gets the rent back on unregister
+}
+
+#[account]
+pub struct GameInvitation { // an on-chain game request between friends, stored at [b"invite", from, to]
+    pub from: Pubkey, // plays X once the invitation is accepted
+    pub to: Pubkey,
+    pub game_config: GameSetupParams,
+    pub expires_at: i64,
+}
+
+#[account]
+pub struct FriendsList { // stored at [b"friends", owner], empty slots hold the default pubkey
+    pub owner: Pubkey,
+    pub friends: [Pubkey; 50],
+}
+
+#[account]
+pub struct PlayerProfile { // a player's record across all games, stored at [b"profile", owner]
+    pub owner: Pubkey,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub games_played: u32,
+    pub abandons: AbandonRecord, // timeouts, setup_game locks out serial abandoners
+    pub elo_rating: i32, // STARTING_ELO until the first rated game
+    pub seasonal_rank: SeasonalRank, // Unranked until the first recorded game, then follows elo_rating
+    pub tournament_wins: u32, // tournaments won, counted when the final is advanced
+    pub tournament_runner_up: u32, // tournament finals lost
+}
+
+#[account]
+pub struct GlobalStats { // program wide counters, stored at the [b"stats"] PDA
+    pub survey_count: u64,
+    pub enjoyment_sum: u64,
+    pub fairness_sum: u64,
+    pub games_by_origin: [u64; 6], // games created, indexed by CreatedVia
+}
+
+#[account]
+pub struct Tournament { // a single-elimination bracket, stored at [b"tournament", organizer, nonce]
+    pub organizer: Pubkey,
+    pub participants: [Pubkey; 8], // only the first participant_count entries are in use
+    pub participant_count: u8,
+    pub capacity: u8, // 4 or 8
+    pub games: [Pubkey; 7], // round one first, then round two, then the final; games decided in the same round sit next to each other
+    pub winners: [Pubkey; 7], // winner of the game in the same slot, zero until advance_winner records it
+    pub round: u8, // 0 while players register
+    pub winner: Option<Pubkey>,
+    pub prize_shares: Vec<u16>, // basis points of the prize pool per placement, first place first, empty without prizes
+    pub ranked: bool, // only Silver and up can register
+}
+
+#[account]
+pub struct PrizeDistribution { // what each placed player of a finished tournament gets, stored at [b"prize_distribution", tournament]
+    pub tournament: Pubkey,
+    pub entries: Vec<PrizeEntry>, // in placement order
+}
+
+#[account]
+pub struct GameLobby { // open games waiting for a second player, stored at the [b"lobby"] PDA
+    pub pending_games: [Pubkey; 32], // only the first `count` entries are in use
+    pub count: u8,
+}
+
+#[account]
+pub struct InstructionRateLimit { // calls of one expensive instruction in the current slot, stored at [b"ratelimit", instruction_id]
+    pub instruction_id: u8,
+    pub calls_this_slot: u32,
+    pub slot: u64, // slot calls_this_slot counts for
+}
+
+#[account]
+pub struct PostGameSurvey { // one player's feedback on a finished game, stored at [b"survey", game, respondent]
+    pub game: Pubkey,
+    pub respondent: Pubkey,
+    pub enjoyment: u8, // 1 to 5
+    pub fairness: u8, // 1 to 5
+    pub would_play_again: bool,
+}
+
+#[account]
+pub struct DailyLeaderboard { // fastest solvers of one daily challenge, stored at [b"daily_lb", date]
+    pub date: u32, // days since the unix epoch
+    pub entries: Vec<DailyEntry>, // fastest first, fewer attempts first on ties
+}
+
+#[account]
+pub struct DailyAttempts { // answers one solver gave to one daily challenge, stored at [b"daily_attempts", date, solver]
+    pub attempts: u8,
+}
+
+#[account]
+pub struct GameHistory { // moves of a game as seen by one recorder, stored at [b"history", game, recorder]
+    pub game: Pubkey,
+    pub recorder: Pubkey,
+    pub moves: Vec<Tile>,
+}
+
+#[account]
+pub struct PlayProof { // a player's commitment to having played a game, stored at [b"proof", game, role]
+    pub commitment: [u8; 32], // sha256 of a secret nonce only the player knows
+    pub game: Pubkey,
+    pub role: u8, // index of the player in game.players
+}
+
+#[account]
+pub struct GameSession { // lets the same two players play game after game in one account, stored at [b"session", player_one, player_two]
+    pub player_one: Pubkey,
+    pub player_two: Pubkey,
+    pub games_played: u32, // finished games recorded by advance_session
+    pub score: [u32; 2], // wins per player, ties aren't counted
+    pub tiebreak_rule: TiebreakRule, // decides the series when finalize_series finds equal scores
+    pub tied_game_moves: [u32; 2], // moves per player in games that ended in a tie, for MostMovesInTiedGames
+    pub winner: Option<Pubkey>, // set by finalize_series, the session can't be advanced after that
+    pub game_state: Game, // the game currently being played, reset in place after each game
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TiebreakRule { // how a series with equal wins is decided
+    ExtraGame, // the next game of the session decides, a tied one goes on to another
+    EloDecider, // the higher rated player wins the series
+    CoinFlip, // hash(session || slot)[0] % 2 picks the winner
+    MostMovesInTiedGames, // whoever made more moves in the tied games
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)] 
+// First 2 tells Rust to automatically generate code for serializing and deserializing 'GameState' instances. This is because data needs to be serialized to be stored in a Solana account, and then deserialized to be read and used in the program
+// Clone tells Rust to generate a '.clone()' method for 'GameState' instances. This method will create a copy of 'GameState'
+// PartialEq allows GameState instances to be compared for equality using '==' operator
+// Eq - This trait indicates that all comparisions of 'GameState' instances are reflexive, symmetric and transitive, which are the conditions needed to define a today equivalence relation.
+pub enum GameState { // represents the possible states that a game could be in
+    Active,
+    Tie,
+    Won { winner: Pubkey },
+    Cancelled { cancelled_by: Pubkey },
+}
+
+impl GameState {
+    pub fn tag(&self) -> u8 {
+        match self {
+            GameState::Active => 0,
+            GameState::Tie => 1,
+            GameState::Won { .. } => 2,
+            GameState::Cancelled { .. } => 3,
+        }
+    } // the variant as a number, for events that can't carry the whole enum
+}
+
+#[derive(
+    AnchorSerialize,
+    AnchorDeserialize,
+    FromPrimitive, // allows converting instances into primitive types
+    ToPrimitive, // vice-versa of above
+    Copy, // this trait means that this type will be 'copy'able
+    Clone,
+    PartialEq,
+    Eq
+)]
+pub enum Sign {
+    X,
+    O,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct GameSetupParams { // the options of setup_game besides the players
+    pub minimum_elo_requirement: i32, // lowest rating allowed to join
+    pub maximum_elo_requirement: Option<i32>, // highest rating allowed to join, if any
+    pub referee: Option<Pubkey>,
+    pub initial_time_bank_secs: u32,
+    pub friends_only: bool, // player_two has to be on player_one's FriendsList
+    pub turn_deadline_slots: u64,
+    pub board_size: u8, // 3 to 5, ignored in a room, which sets its own
+    pub win_length: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PrizeEntry {
+    pub player: Pubkey,
+    pub amount: u64, // lamports
+    pub claimed: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DailyEntry {
+    pub solver: Pubkey,
+    pub solved_at: i64,
+    pub attempts_used: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct AbandonRecord { // how reliably a player finishes games, kept per player
+    pub games_abandoned: u32, // games lost through claim_timeout
+    pub completed_games_since_last_abandon: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CreatedVia { // how a game came to be, Lobby and Scheduled don't have instructions so far
+    Direct,
+    Lobby,
+    Tournament,
+    Series,
+    Rematch,
+    Scheduled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum SeasonalRank { // derived from a player's ELO, ordered from lowest to highest
+    Unranked,
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Tile {
+    row: u8,
+    column: u8,
+} // this struct is also stored on Solana
+
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+pub struct MoveRecord { // one entry of Game::moves, a struct rather than a (row, column, sign) tuple so it shows up in the IDL
+    pub row: u8,
+    pub column: u8,
+    pub sign: Sign,
+}
+
+impl MoveRecord {
+    pub const EMPTY: MoveRecord = MoveRecord { row: 0, column: 0, sign: Sign::X }; // filler for the unplayed slots
+}
+
+#[event]
+pub struct SeriesDecided {
+    pub session: Pubkey,
+    pub winner: Pubkey,
+    pub tiebreak: Option<TiebreakRule>, // the rule that was applied, None when one player simply won more games
+}
+
+#[event]
+pub struct RankChanged { // emitted whenever an ELO change moves a player into another rank, up or down
+    pub player: Pubkey,
+    pub old: SeasonalRank,
+    pub new: SeasonalRank,
+}
+
+#[event]
+pub struct SignsSwapped {
+    pub game: Pubkey,
+    pub new_x_player: Pubkey,
+    pub new_o_player: Pubkey,
+}
+
+#[event]
+pub struct WinConditionVerified {
+    pub game: Pubkey,
+}
+
+#[event]
+pub struct WinConditionMismatch { // the stored state doesn't follow from the board, tags as in GameState::tag
+    pub game: Pubkey,
+    pub stored_tag: u8,
+    pub computed_tag: u8,
+    pub computed_winner: Option<Pubkey>,
+}
+
+#[event]
+pub struct PlayCountMismatch { // the stored move counter disagrees with the number of signs on the board
+    pub game: Pubkey,
+    pub stored: u8,
+    pub computed: u8,
+}
+
+#[event]
+pub struct ZugzwangDetected { // every move the player has lets the opponent win right after
+    pub game: Pubkey,
+    pub player: Pubkey,
+}
+
+#[event]
+pub struct GameGarbageCollected {
+    pub game: Pubkey,
+    pub finder: Pubkey,
+    pub rent_recovered: u64, // all lamports the game account held, 1% of it went to the treasury
+}
+
+#[event]
+pub struct MutualFriends {
+    pub owner: Pubkey,
+    pub other: Pubkey,
+    pub friends: Vec<Pubkey>,
+}
+
+#[event]
+pub struct SurveyAverages { // averages times 100, so 425 means 4.25
+    pub avg_enjoyment_x100: u64,
+    pub avg_fairness_x100: u64,
+    pub count: u64,
+}
+
+#[event]
+pub struct RatingDecayApplied {
+    pub room: Pubkey,
+    pub players_affected: u16,
+}
+
+#[event]
+pub struct RankingUnlocked { // a room member finished calibrating
+    pub player: Pubkey,
+    pub room: Pubkey,
+}
+
+#[event]
+pub struct DailyLeaderboardEntries {
+    pub date: u32,
+    pub entries: Vec<DailyEntry>,
+}
+
+#[event]
+pub struct DailyChallengeAttempted {
+    pub date: u32,
+    pub solver: Pubkey,
+    pub attempts_used: u8,
+    pub solved: bool,
+}
+
+#[event]
+pub struct PlayerWarned { // sent at 3, 5 and 10 abandons
+    pub player: Pubkey,
+    pub abandons: u32,
+}
+
+#[event]
+pub struct ExpectedOutcome { // for the player to move: 1 forced win, 0 tie, -1 lost against perfect play
+    pub game: Pubkey,
+    pub outcome: i8,
+}
+
+#[event]
+pub struct EndgameDetected {
+    pub game: Pubkey,
+    pub moves_left: u8,
+}
+
+#[event]
+pub struct HistoriesAgree {
+    pub game: Pubkey,
+}
+
+#[event]
+pub struct HistoriesDisagree { // a side whose recording ends before the divergence reports u8::MAX as row and column
+    pub game: Pubkey,
+    pub diverge_at_turn: u8,
+    pub a_row: u8,
+    pub a_col: u8,
+    pub b_row: u8,
+    pub b_col: u8,
+}
+
+#[event]
+pub struct SeedResult { // a random game replayable from its seed, moves are tiles as row * 3 + column
+    pub seed: [u8; 32],
+    pub moves: Vec<u8>,
+    pub final_state_tag: u8,
+    pub x_wins: bool,
+}
+
+#[event]
+pub struct BoardReconstructed {
+    pub game: Pubkey,
+    pub matches: bool,
+}
+
+#[event]
+pub struct BoardMismatch { // first cell where the stored board differs from the replay, cells as in Game::cell_code
+    pub game: Pubkey,
+    pub row: u8,
+    pub col: u8,
+    pub expected: u8,
+    pub found: u8,
+}
+
+#[event]
+pub struct FeaturedGameSet {
+    pub room: Pubkey,
+    pub game: Pubkey,
+}
+
+#[event]
+pub struct FeaturedGameCleared {
+    pub room: Pubkey,
+}
+
+#[event]
+pub struct ObserverNotificationFailed { // finalize_game went on without this observer
+    pub game: Pubkey,
+    pub observer: Pubkey,
+}
+
+#[event]
+pub struct AccountLayoutMismatch { // the account can't be read as the current Game, or was written by an older version
+    pub game: Pubkey,
+    pub account_size: u64,
+    pub expected_size: u64,
+}
+
+#[event]
+pub struct AccountLayoutValid {
+    pub game: Pubkey,
+    pub version: u8,
+}
+
+#[event]
+pub struct GameStarted {
+    pub game: Pubkey,
+    pub player_one: Pubkey,
+    pub player_two: Pubkey,
+    pub timestamp: i64,
+    pub created_via: CreatedVia,
+}
+
+#[event]
+pub struct MovePlayed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub row: u8,
+    pub column: u8,
+    pub turn: u8, // turn the move was played on, starting at 1
+    pub last_move_tile: Option<Tile>, // the game's last_move_tile after the move
+}
+
+#[event]
+pub struct GameEnded { // the game left GameState::Active, by a move, resignation, timeout, draw or the referee
+    pub game: Pubkey,
+    pub state: GameState,
+    pub final_turn: u8,
+}
+
+#[event]
+pub struct TournamentWon {
+    pub tournament: Pubkey,
+    pub winner: Pubkey,
+}
+
+#[event]
+pub struct BracketIntegrityViolation { // the game in this slot doesn't back what the bracket records
+    pub tournament: Pubkey,
+    pub slot_index: u8,
+}
+
+#[event]
+pub struct BracketVerified {
+    pub tournament: Pubkey,
+}
+
+#[event]
+pub struct PrizeDistributionComputed {
+    pub tournament: Pubkey,
+    pub total: u64, // lamports split between the placed players
+}
+
+#[event]
+pub struct OpenGameJoined {
+    pub game: Pubkey,
+    pub player: Pubkey,
+}
+
+#[error_code] // macro provided by anchor that automates the process of mapping these custom errors to error codes. This attribute will create an 'impl ErrorCode for TicTacToeError' block where each variant is assigned a unique error code
+pub enum TicTacToeError {
+    TileOutOfBounds,
+    TileAlreadySet,
+    GameAlreadyOver,
+    NotPlayersTurn,
+    GameAlreadyStarted,
+    StaleNonce,
+    PlayerBanned,
+    BanCheckAccountMissing,
+    InvalidBoardConfig,
+    RoomEloMismatch,
+    NotAPlayer,
+    GameNotOver,
+    SessionExpired,
+    GameNotOpen,
+    InviteExpired,
+    InviteNotExpired,
+    AlreadyProved,
+    WithdrawalLocked,
+    EloTooLowForRanked,
+    TooManyObservers,
+    GameAlreadyFinalized,
+    RoomAccountMismatch,
+    ObserverAccountMismatch,
+    InvalidPrizeShares,
+    EloTooLow,
+    EloTooHigh,
+    SwapWindowClosed,
+    HistoryFull,
+    TooManyAbandons,
+    LeaderboardFrozen,
+    InvalidSurveyScore,
+    SurveyWindowClosed,
+    HistoryGameMismatch,
+    RefereeNotSet,
+    NotReferee,
+    RefereeAuthorityLapsed,
+    InvalidDecayRate,
+    DecayTooSoon,
+    NotFriends,
+    AlreadyFriends,
+    FriendsListFull,
+    InvalidFriend,
+    GameNotAbandoned,
+    InvitationExpired,
+    GlobalRateLimitExceeded,
+    OpponentMissing,
+    WagerRequiresOpenGame,
+    WagerTooSmall,
+    WagerAlreadySettled,
+    WagerAtStake,
+    TurnDeadlineNotExpired,
+    GameNotStarted,
+    ResultAlreadyRecorded,
+    GameLobbyFull,
+    DrawAlreadyOffered,
+    NoDrawOffered,
+    OwnDrawOffer,
+    OpponentMustSign,
+    TurnOverflow,
+    InvalidParticipantCount,
+    TournamentFull,
+    TournamentAlreadyStarted,
+    AlreadyRegistered,
+    TournamentNotRunning,
+    InvalidTournamentGame,
+    WinnerAlreadyAdvanced,
+    RoundNotComplete,
+    ChallengeNotStarted,
+    TournamentNotFinished,
+    PrizeAlreadyClaimed,
+    PositionTooOpen,
+    TiebreakNotApplicable,
+    SeriesAlreadyDecided,
+    SeriesGameInProgress,
+}
+
+impl Game { // to define methods on the struct Game
+    pub const ENDGAME_MOVES: u8 = 3;
+
+    pub const CLASSIC_BOARD_SIZE: u8 = 3;
+    pub const MAX_BOARD_SIZE: u8 = 5;
+    pub const MAX_CELLS: usize = 25; // MAX_BOARD_SIZE squared
+
+    // (row step, column step) of rows, columns and both diagonals
+    const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+    // serialized size of every field in declaration order, without the 8 byte discriminator.
+    // enums and Options take one byte for the variant, plus the largest payload
+    pub const MAXIMUM_SIZE: usize = (32 * 2) // players
+        + 1 // turn
+        + (25 * (1 + 1)) + 1 + 1 // board, board_size, win_length
+        + (32 + 1) // state, Won carries the winner
+        + (1 + 2) // last_move_tile
+        + 8 // nonce
+        + (1 + 32) // room
+        + 8 // invite_expiry
+        + 4 + (1 + 4) // minimum_elo_requirement, maximum_elo_requirement
+        + 1 + 1 + 1 // observer_count, finalized, swap_applied
+        + 8 // ended_at
+        + (1 + 32) + 8 // referee, dispute_resolution_timeout
+        + 4 + 4 + 8 // time_bank_p1, time_bank_p2, last_move_at
+        + 1 + 1 + 1 // created_via, play_count, version
+        + 8 + 1 + 1 // wager, wager_settled, resigned
+        + 8 + 8 // turn_deadline_slots, last_move_slot
+        + (25 * (1 + 1 + 1)) // moves
+        + 1 + 2 // timed_out, results_recorded
+        + (1 + 32) // draw_offered_by
+        + 1; // time_bank_enabled
+
+    pub fn setup_game(ctx: Context<SetupGame>, player_two: Pubkey, mut params: GameSetupParams, wager: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player_one.key(), ctx.remaining_accounts)?;
+        if wager > 0 {
+            // player_two deposits in join_open_game, so only open games can have a wager
+            require!(player_two == Pubkey::default(), TicTacToeError::WagerRequiresOpenGame);
+            require!(wager >= Rent::get()?.minimum_balance(0), TicTacToeError::WagerTooSmall); // the vault has to be rent exempt
+            Self::deposit_wager(&ctx.accounts.system_program, &ctx.accounts.player_one, &ctx.accounts.vault, wager)?;
+            ctx.accounts.game.wager = wager;
+        }
+        if let Ok(profile) = Account::<PlayerProfile>::try_from(&ctx.accounts.profile.to_account_info()) {
+            profile.abandons.ensure_allowed(&ctx.accounts.config.settings)?;
+        }
+        if let Some(room) = &ctx.accounts.room {
+            room.check_elo(PlayerProfile::rating_of(&ctx.accounts.profile))?;
+        }
+        if params.friends_only {
+            let is_friend = ctx.accounts.friends.as_ref().is_some_and(|friends| friends.contains(&player_two));
+            require!(is_friend, TicTacToeError::NotFriends);
+        }
+        if let Some(room) = &mut ctx.accounts.room {
+            ctx.accounts.game.room = Some(room.key());
+            room.active_games += 1;
+            params.board_size = room.config.board_size; // games in a room play by the room's rules
+            params.win_length = room.config.win_length;
+        }
+        if player_two == Pubkey::default() {
+            ctx.accounts.lobby.add_game(ctx.accounts.game.key())?;
+        }
+        ctx.accounts.game.created_via = CreatedVia::Direct;
+        ctx.accounts.stats.record_game_created(CreatedVia::Direct);
+        let clock = Clock::get()?;
+        ctx.accounts.game.init([ctx.accounts.player_one.key(), player_two], &params, &ctx.accounts.config.settings, &clock)?;
+        Self::emit_started(&ctx.accounts.game, &clock);
+        Ok(())
+    }
+
+    fn init(&mut self, players: [Pubkey; 2], params: &GameSetupParams, settings: &ConfigSettings, clock: &Clock) -> Result<()> {
+        Self::validate_board_config(params.board_size, params.win_length)?;
+        self.board_size = params.board_size;
+        self.win_length = params.win_length;
+        let now = clock.unix_timestamp;
+        self.minimum_elo_requirement = params.minimum_elo_requirement;
+        self.maximum_elo_requirement = params.maximum_elo_requirement;
+        if params.referee.is_some() {
+            self.referee = params.referee;
+            self.dispute_resolution_timeout = now + settings.referee_window_secs;
+        }
+        self.time_bank_p1 = params.initial_time_bank_secs;
+        self.time_bank_p2 = params.initial_time_bank_secs;
+        self.time_bank_enabled = params.initial_time_bank_secs > 0;
+        self.last_move_at = now;
+        self.turn_deadline_slots = params.turn_deadline_slots;
+        self.last_move_slot = clock.slot;
+        if players[1] == Pubkey::default() {
+            // nobody has been invited yet, anyone can join until the invite expires
+            self.invite_expiry = now + settings.invite_ttl_secs;
+        }
+        self.start(players)
+    } // everything setup_game and accept_invitation have in common
+
+    fn emit_started(game: &Account<Game>, clock: &Clock) {
+        emit!(GameStarted {
+            game: game.key(),
+            player_one: game.players[0],
+            player_two: game.players[1],
+            timestamp: clock.unix_timestamp,
+            created_via: game.created_via,
+        });
+    } // player_two is still zero for an open game, OpenGameJoined follows once someone takes the seat
+
+    pub fn verify_account_layout(ctx: Context<VerifyAccountLayout>) -> Result<()> {
+        let info = ctx.accounts.game.to_account_info();
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == <Game as anchor_lang::Discriminator>::DISCRIMINATOR,
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        ); // not a game at all, as opposed to a game in an old layout
+        let expected_size = (8 + Game::MAXIMUM_SIZE) as u64;
+        match Game::try_deserialize(&mut &data[..]) {
+            Ok(game) if game.version == CURRENT_GAME_VERSION => {
+                emit!(AccountLayoutValid { game: info.key(), version: game.version });
+            }
+            _ => {
+                emit!(AccountLayoutMismatch { game: info.key(), account_size: data.len() as u64, expected_size });
+            }
+        }
+        Ok(())
+    } // an account that fails to deserialize was most likely created before the latest layout change
+
+    pub fn join_open_game(ctx: Context<JoinOpenGame>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        require!(game.is_active(), TicTacToeError::GameAlreadyOver);
+        require!(game.players[1] == Pubkey::default(), TicTacToeError::GameNotOpen);
+        require!(Clock::get()?.unix_timestamp <= game.invite_expiry, TicTacToeError::InviteExpired);
+        let rating = PlayerProfile::rating_of(&ctx.accounts.profile);
+        game.check_elo_requirement(rating)?;
+        require!(game.room.is_some() == ctx.accounts.room.is_some(), TicTacToeError::RoomAccountMismatch);
+        if let Some(room) = &ctx.accounts.room {
+            room.check_elo(rating)?;
+        }
+        game.use_nonce(expected_nonce)?;
+        game.players[1] = ctx.accounts.player.key();
+        // the time waiting for an opponent doesn't count against player_one
+        let clock = Clock::get()?;
+        game.last_move_slot = clock.slot;
+        game.last_move_at = clock.unix_timestamp;
+        if game.wager > 0 {
+            Self::deposit_wager(&ctx.accounts.system_program, &ctx.accounts.player, &ctx.accounts.vault, game.wager)?;
+        }
+        ctx.accounts.lobby.remove_game(&game.key());
+        emit!(OpenGameJoined { game: game.key(), player: ctx.accounts.player.key() });
+        Ok(())
+    }
+
+    pub fn rematch(ctx: Context<Rematch>) -> Result<()> {
+        if let Ok(profile) = Account::<PlayerProfile>::try_from(&ctx.accounts.profile.to_account_info()) {
+            profile.abandons.ensure_allowed(&ctx.accounts.config.settings)?;
+        }
+        let old_game = &ctx.accounts.old_game;
+        require!(!old_game.is_active(), TicTacToeError::GameNotOver);
+        let role = old_game.role_of(&ctx.accounts.initiator.key());
+        require!(role != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(old_game.players[1] != Pubkey::default(), TicTacToeError::OpponentMissing); // nobody to play again
+        let players = old_game.rematch_players();
+        for player in &players {
+            ensure_not_banned(player, ctx.remaining_accounts)?; // the opponent doesn't have to sign, but can't be banned either
+        }
+
+        if old_game.wager > 0 {
+            // both stakes go in right away, there is no join step where the opponent could pay later
+            let opponent = ctx.accounts.opponent.as_ref().ok_or(TicTacToeError::OpponentMustSign)?;
+            require_keys_eq!(opponent.key(), old_game.players[1 - role as usize], TicTacToeError::OpponentMustSign);
+            for player in [&ctx.accounts.initiator, opponent] {
+                Self::deposit_wager(&ctx.accounts.system_program, player, &ctx.accounts.vault, old_game.wager)?;
+            }
+        }
+        let params = GameSetupParams {
+            minimum_elo_requirement: old_game.minimum_elo_requirement,
+            maximum_elo_requirement: old_game.maximum_elo_requirement,
+            referee: None,
+            initial_time_bank_secs: 0,
+            friends_only: false,
+            turn_deadline_slots: old_game.turn_deadline_slots,
+            board_size: old_game.board_size,
+            win_length: old_game.win_length,
+        };
+        let wager = old_game.wager;
+
+        ctx.accounts.stats.record_game_created(CreatedVia::Rematch);
+        let clock = Clock::get()?;
+        let new_game = &mut ctx.accounts.new_game;
+        new_game.created_via = CreatedVia::Rematch;
+        new_game.wager = wager;
+        new_game.init(players, &params, &ctx.accounts.config.settings, &clock)?;
+        Self::emit_started(new_game, &clock);
+        Ok(())
+    } // same board, deadline and stakes as old_game, outside of any room
+
+    pub fn rematch_players(&self) -> [Pubkey; 2] {
+        match self.state {
+            GameState::Won { winner } if winner == self.players[0] => [self.players[1], self.players[0]],
+            GameState::Won { .. } => self.players,
+            _ => [self.players[1], self.players[0]],
+        }
+    } // the loser opens the rematch, after a tie or a cancelled game whoever went second does
+
+    fn deposit_wager<'info>(
+        system_program: &Program<'info, System>,
+        player: &Signer<'info>,
+        vault: &SystemAccount<'info>,
+        wager: u64,
+    ) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer { from: player.to_account_info(), to: vault.to_account_info() },
+            ),
+            wager,
+        )
+    }
+
+    fn pay_from_vault<'info>(
+        system_program: &Program<'info, System>,
+        vault: &SystemAccount<'info>,
+        vault_bump: u8,
+        game: Pubkey,
+        to: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let signer_seeds: &[&[&[u8]]] = &[&[b"vault", game.as_ref(), &[vault_bump]]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Transfer { from: vault.to_account_info(), to },
+                signer_seeds,
+            ),
+            amount,
+        )
+    } // only this program can sign for the vault, and only with the seeds of the game it belongs to
+
+    pub fn settle(ctx: Context<Settle>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(!game.is_active(), TicTacToeError::GameNotOver);
+        require!(!game.wager_settled, TicTacToeError::WagerAlreadySettled);
+        game.wager_settled = true;
+
+        let (to_player_one, to_player_two) = game.wager_payout(&ctx.accounts.config.settings);
+        // whatever isn't paid to the players goes to the treasury, including lamports sent to the vault by anyone else
+        let vault_balance = ctx.accounts.vault.lamports();
+        let to_treasury = vault_balance
+            .checked_sub(to_player_one)
+            .and_then(|rest| rest.checked_sub(to_player_two))
+            .ok_or(ProgramError::InsufficientFunds)?;
+
+        let game_key = game.key();
+        let bump = *ctx.bumps.get("vault").unwrap();
+        let system_program = &ctx.accounts.system_program;
+        let vault = &ctx.accounts.vault;
+        Self::pay_from_vault(system_program, vault, bump, game_key, ctx.accounts.player_one.to_account_info(), to_player_one)?;
+        Self::pay_from_vault(system_program, vault, bump, game_key, ctx.accounts.player_two.to_account_info(), to_player_two)?;
+        Self::pay_from_vault(system_program, vault, bump, game_key, ctx.accounts.treasury.to_account_info(), to_treasury)
+    }
+
+    pub fn wager_payout(&self, settings: &ConfigSettings) -> (u64, u64) {
+        match self.state {
+            GameState::Won { winner } => {
+                let penalty_bps = if self.resigned { settings.resign_penalty_bps } else { 10_000 };
+                let (to_winner, to_loser, _) = utils::compute_resign_payout(self.wager, penalty_bps, settings.platform_fee_bps);
+                if winner == self.players[0] { (to_winner, to_loser) } else { (to_loser, to_winner) }
+            }
+            _ => {
+                let (half, other_half, _) = utils::compute_resign_payout(self.wager, 5_000, settings.platform_fee_bps);
+                (half, other_half)
+            }
+        }
+    } // lamports for (players[0], players[1]) of a finished game, a tie splits the pot after the platform fee
+
+    pub fn abandoned_vault_split(&self, vault_balance: u64) -> (u64, u64) {
+        if self.players[1] == Pubkey::default() {
+            return (vault_balance, 0);
+        }
+        let to_player_two = vault_balance / 2;
+        (vault_balance - to_player_two, to_player_two)
+    } // lamports for (players[0], players[1]) when an abandoned game is collected, an odd lamport goes to players[0]
+
+    pub fn finalize_game<'info>(ctx: Context<'_, '_, '_, 'info, FinalizeGame<'info>>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(!game.is_active(), TicTacToeError::GameNotOver);
+        require!(!game.finalized, TicTacToeError::GameAlreadyFinalized);
+        require!(game.room.is_some() == ctx.accounts.room.is_some(), TicTacToeError::RoomAccountMismatch);
+        game.finalized = true;
+        game.ended_at = Clock::get()?.unix_timestamp;
+
+        if let Some(room) = &mut ctx.accounts.room {
+            if room.release_game(game.key()) {
+                emit!(FeaturedGameCleared { room: room.key() });
+            }
+        }
+        let slot = Clock::get()?.slot;
+        for member in [&mut ctx.accounts.member_one, &mut ctx.accounts.member_two].into_iter().flatten() {
+            member.last_active_slot = slot;
+            if member.record_calibration_game() {
+                emit!(RankingUnlocked { player: member.player, room: member.room });
+            }
+        }
+
+        // each observer is called exactly once, so all of them have to be passed and none twice
+        let groups = ctx.remaining_accounts.chunks_exact(3);
+        require!(groups.remainder().is_empty(), TicTacToeError::ObserverAccountMismatch);
+        require_eq!(groups.len(), game.observer_count as usize, TicTacToeError::ObserverAccountMismatch);
+        let winner = match game.state {
+            GameState::Won { winner } => Some(winner),
+            _ => None,
+        };
+        let game_info = game.to_account_info();
+        let mut notified: Vec<Pubkey> = Vec::with_capacity(groups.len());
+        for group in groups {
+            let [observer_info, program_info, state_info] = group else { unreachable!() };
+            let observer = Account::<GameObserver>::try_from(observer_info)?;
+            require_keys_eq!(observer.game, game_info.key(), TicTacToeError::ObserverAccountMismatch);
+            require_keys_eq!(observer.observer, program_info.key(), TicTacToeError::ObserverAccountMismatch);
+            require!(!notified.contains(&observer.observer), TicTacToeError::ObserverAccountMismatch);
+            notified.push(observer.observer);
+            // a broken observer must not keep the game from being finalized
+            if let Err(error) = GameObserver::notify(program_info, &game_info, state_info, winner) {
+                msg!("observer {} was not notified: {}", observer.observer, error);
+                emit!(ObserverNotificationFailed { game: game_info.key(), observer: observer.observer });
+            }
+        }
+        Ok(())
+    } // permissionless, hands the result of a finished game to everything that depends on it
+
+    pub fn swap_signs(ctx: Context<SwapSigns>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        require!(game.is_active(), TicTacToeError::GameAlreadyOver);
+        require_keys_eq!(game.players[1], ctx.accounts.player.key(), TicTacToeError::NotPlayersTurn);
+        // only right after the first move, and only once - otherwise the opener could swap straight back
+        require!(game.turn == 2 && !game.swap_applied, TicTacToeError::SwapWindowClosed);
+        game.use_nonce(expected_nonce)?;
+
+        game.players.swap(0, 1);
+        (game.time_bank_p1, game.time_bank_p2) = (game.time_bank_p2, game.time_bank_p1); // the banks stay with their players
+        game.swap_applied = true;
+        emit!(SignsSwapped {
+            game: game.key(),
+            new_x_player: game.players[0],
+            new_o_player: game.players[1],
+        });
+        Ok(())
+    } // pie rule: after seeing the first move, player_two may take it over, the opener then plays O
+
+    pub fn resign(ctx: Context<Resign>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        require!(game.is_active(), TicTacToeError::GameAlreadyOver);
+        game.use_nonce(expected_nonce)?;
+        game.forfeit(&ctx.accounts.player.key())?;
+        emit!(GameEnded { game: game.key(), state: game.state.clone(), final_turn: game.turn });
+        Ok(())
+    }
+
+    pub fn claim_timeout(ctx: Context<ClaimTimeout>, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        require!(game.role_of(&ctx.accounts.player.key()) != u8::MAX, TicTacToeError::NotAPlayer);
+        let clock = Clock::get()?;
+        let winner = game.timeout_winner(clock.slot, clock.unix_timestamp, &ctx.accounts.config.settings)?;
+        game.use_nonce(expected_nonce)?;
+        game.state = GameState::Won { winner };
+        game.timed_out = true;
+        emit!(GameEnded { game: game.key(), state: game.state.clone(), final_turn: game.turn });
+        Ok(())
+    } // the game then settles and finalizes like any other win
+
+    pub fn timeout_winner(&self, slot: u64, now: i64, settings: &ConfigSettings) -> Result<Pubkey> {
+        require!(self.is_active(), TicTacToeError::GameAlreadyOver);
+        require!(self.players[1] != Pubkey::default(), TicTacToeError::GameNotStarted);
+        let deadline_passed =
+            self.turn_deadline_slots > 0 && slot > self.last_move_slot.saturating_add(self.turn_deadline_slots);
+        let out_of_time = self.time_bank_enabled && self.time_bank_exhausted(now, settings);
+        require!(deadline_passed || out_of_time, TicTacToeError::TurnDeadlineNotExpired);
+        Ok(self.players[1 - self.current_player_index()?])
+    } // the player who isn't on the move, once the one who is has let the deadline pass or run out of time
+
+    pub fn forfeit(&mut self, player: &Pubkey) -> Result<()> {
+        let role = self.role_of(player);
+        require!(role != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(self.players[1] != Pubkey::default(), TicTacToeError::OpponentMissing); // open games are cancelled instead
+        self.state = GameState::Won { winner: self.players[1 - role as usize] };
+        self.resigned = true;
+        Ok(())
+    } // the other player wins
+
+    pub fn offer_draw(&mut self, player: &Pubkey) -> Result<()> {
+        require!(self.is_active(), TicTacToeError::GameAlreadyOver);
+        require!(self.role_of(player) != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(self.players[1] != Pubkey::default(), TicTacToeError::OpponentMissing);
+        require!(self.draw_offered_by.is_none(), TicTacToeError::DrawAlreadyOffered);
+        self.draw_offered_by = Some(*player);
+        Ok(())
+    }
+
+    pub fn accept_draw(&mut self, player: &Pubkey) -> Result<()> {
+        require!(self.is_active(), TicTacToeError::GameAlreadyOver);
+        require!(self.role_of(player) != u8::MAX, TicTacToeError::NotAPlayer);
+        let offered_by = self.draw_offered_by.ok_or(TicTacToeError::NoDrawOffered)?;
+        require!(offered_by != *player, TicTacToeError::OwnDrawOffer);
+        self.draw_offered_by = None;
+        self.state = GameState::Tie;
+        Ok(())
+    } // settles like a tie on a full board
+
+    pub fn decline_draw(&mut self, player: &Pubkey) -> Result<()> {
+        require!(self.role_of(player) != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(self.draw_offered_by.is_some(), TicTacToeError::NoDrawOffered);
+        self.draw_offered_by = None;
+        Ok(())
+    } // the game goes on
+
+    pub fn double_check_win_condition(ctx: Context<DoubleCheckWinCondition>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let computed = game.recompute_state();
+        if computed == game.state {
+            emit!(WinConditionVerified { game: game.key() });
+        } else {
+            emit!(WinConditionMismatch {
+                game: game.key(),
+                stored_tag: game.state.tag(),
+                computed_tag: computed.tag(),
+                computed_winner: match computed {
+                    GameState::Won { winner } => Some(winner),
+                    _ => None,
+                },
+            });
+        }
+        Ok(()) // a mismatch is reported, not failed, so it ends up in the transaction logs
+    }
+
+    pub fn analyze_threats(ctx: Context<AnalyzeThreats>) -> Result<()> {
+        ctx.accounts.rate_limit.record_call(Clock::get()?.slot, &ctx.accounts.config.settings)?;
+        let game = &ctx.accounts.game;
+        if game.is_zugzwang() {
+            emit!(ZugzwangDetected { game: game.key(), player: game.current_player()? });
+        }
+        if game.is_active() && game.legal_moves_count() == Self::ENDGAME_MOVES {
+            emit!(EndgameDetected { game: game.key(), moves_left: Self::ENDGAME_MOVES }); // only on the move that enters the endgame
+        }
+        Ok(())
+    }
+
+    pub fn cancel_game(ctx: Context<CancelGame>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(referee) = &ctx.accounts.referee {
+            ensure_not_banned(&referee.key(), ctx.remaining_accounts)?;
+            game.ensure_referee(&referee.key(), now)?;
+            require!(!game.finalized, TicTacToeError::GameAlreadyFinalized);
+            // once both wagers are in, the referee has to resolve the game so settle can pay them out
+            require!(game.wager == 0 || game.players[1] == Pubkey::default(), TicTacToeError::WagerAtStake);
+        } else {
+            require!(ctx.accounts.player_one.to_account_info().is_signer, ErrorCode::AccountNotSigner);
+            ensure_not_banned(&ctx.accounts.player_one.key(), ctx.remaining_accounts)?;
+            require!(game.players[1] == Pubkey::default(), TicTacToeError::GameNotOpen);
+            require!(now > game.invite_expiry, TicTacToeError::InviteNotExpired);
+            require_eq!(game.play_count, 0, TicTacToeError::GameAlreadyStarted);
+        }
+        ctx.accounts.lobby.remove_game(&game.key()); // a no-op unless nobody joined
+        require!(game.room.is_some() == ctx.accounts.room.is_some(), TicTacToeError::RoomAccountMismatch);
+        // the game is closed below, so it never gets to finalize_game
+        if let Some(room) = &mut ctx.accounts.room {
+            if room.release_game(game.key()) {
+                emit!(FeaturedGameCleared { room: room.key() });
+            }
+        }
+        let refund = ctx.accounts.vault.lamports();
+        let bump = *ctx.bumps.get("vault").unwrap();
+        let to = ctx.accounts.player_one.to_account_info();
+        Self::pay_from_vault(&ctx.accounts.system_program, &ctx.accounts.vault, bump, game.key(), to, refund)
+    } // the rent goes back to player_one through the `close` constraint, the wager from the vault
+
+    pub fn garbage_collect_game(ctx: Context<GarbageCollectGame>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.finder.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        require!(game.is_active(), TicTacToeError::GameAlreadyOver);
+        let idle_until = game.last_move_at + ctx.accounts.config.settings.gc_grace_period_secs;
+        require!(Clock::get()?.unix_timestamp > idle_until, TicTacToeError::GameNotAbandoned);
+        require!(game.room.is_some() == ctx.accounts.room.is_some(), TicTacToeError::RoomAccountMismatch);
+        if let Some(room) = &mut ctx.accounts.room {
+            if room.release_game(game.key()) {
+                emit!(FeaturedGameCleared { room: room.key() });
+            }
+        }
+        ctx.accounts.lobby.remove_game(&game.key());
+
+        // the vault is emptied before the game goes, nobody could sign for it afterwards
+        let has_opponent = game.players[1] != Pubkey::default();
+        require!(ctx.accounts.player_two.is_some() == has_opponent, TicTacToeError::NotAPlayer);
+        let (to_player_one, to_player_two) = game.abandoned_vault_split(ctx.accounts.vault.lamports());
+        let bump = *ctx.bumps.get("vault").unwrap();
+        let (system_program, vault) = (&ctx.accounts.system_program, &ctx.accounts.vault);
+        let to = ctx.accounts.player_one.to_account_info();
+        Self::pay_from_vault(system_program, vault, bump, game.key(), to, to_player_one)?;
+        if let Some(player_two) = &ctx.accounts.player_two {
+            Self::pay_from_vault(system_program, vault, bump, game.key(), player_two.to_account_info(), to_player_two)?;
+        }
+
+        let finder = ctx.accounts.finder.key();
+        game.state = GameState::Cancelled { cancelled_by: finder };
+        let game_info = game.to_account_info();
+        let rent_recovered = game_info.lamports();
+        let treasury_share = rent_recovered / 100;
+        **game_info.try_borrow_mut_lamports()? -= treasury_share;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += treasury_share;
+        game.close(ctx.accounts.finder.to_account_info())?; // the other 99% is the finder's fee
+        emit!(GameGarbageCollected { game: game_info.key(), finder, rent_recovered });
+        Ok(())
+    }
+
+    pub fn resolve_disputed_game(
+        ctx: Context<ResolveDisputedGame>,
+        winner: Option<Pubkey>,
+        expected_nonce: u64,
+    ) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.referee.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        game.ensure_referee(&ctx.accounts.referee.key(), Clock::get()?.unix_timestamp)?;
+        require!(!game.finalized, TicTacToeError::GameAlreadyFinalized);
+        require!(game.is_active(), TicTacToeError::GameAlreadyOver); // a settled or recorded result stays as it is
+        require!(game.players[1] != Pubkey::default(), TicTacToeError::OpponentMissing); // nobody to rule between yet
+        if let Some(winner) = winner {
+            require!(game.role_of(&winner) != u8::MAX, TicTacToeError::NotAPlayer);
+        }
+        game.use_nonce(expected_nonce)?;
+        game.state = match winner {
+            Some(winner) => GameState::Won { winner },
+            None => GameState::Tie,
+        };
+        (game.resigned, game.timed_out) = (false, false); // the verdict isn't a resignation or a timeout
+        emit!(GameEnded { game: game.key(), state: game.state.clone(), final_turn: game.turn });
+        Ok(())
+    } // the referee's verdict replaces whatever the board says, finalize_game then hands it out as usual
+
+    pub fn charge_move_time(&mut self, now: i64, settings: &ConfigSettings) -> Result<()> {
+        let time_used = (now - self.last_move_at).max(0);
+        let limit = settings.move_time_limit_secs as i64;
+        let bank = match self.current_player_index()? {
+            0 => &mut self.time_bank_p1,
+            _ => &mut self.time_bank_p2,
+        };
+        if time_used <= limit {
+            let surplus = (limit - time_used) as u32;
+            *bank = bank.saturating_add(surplus.min(settings.max_time_bank_accrual_secs));
+        } else {
+            *bank = bank.saturating_sub((time_used - limit).min(u32::MAX as i64) as u32);
+        }
+        self.last_move_at = now;
+        Ok(())
+    } // to be called before play(): quick moves save time up, slow ones pay the overage from the bank
+
+    pub fn time_bank_exhausted(&self, now: i64, settings: &ConfigSettings) -> bool {
+        let bank = match self.current_player_index() {
+            Ok(0) => self.time_bank_p1,
+            Ok(_) => self.time_bank_p2,
+            Err(_) => return false, // nobody is on the move before the game starts
+        };
+        let overtime = now - self.last_move_at - settings.move_time_limit_secs as i64;
+        overtime > bank as i64
+    } // the player on the move is past the move time limit by more than their bank holds
+
+    pub fn ensure_referee(&self, caller: &Pubkey, now: i64) -> Result<()> {
+        let referee = self.referee.ok_or(TicTacToeError::RefereeNotSet)?;
+        require_keys_eq!(referee, *caller, TicTacToeError::NotReferee);
+        require!(now <= self.dispute_resolution_timeout, TicTacToeError::RefereeAuthorityLapsed);
+        Ok(())
+    }
+
+    pub fn start(&mut self, players: [Pubkey; 2]) -> Result<()> {
+        require_eq!(self.turn, 0, TicTacToeError::GameAlreadyStarted); // checks that game has been started yet
+        self.players = players; // sets the 'players' field to the 2 players who will be playing the game
+        self.turn = 1; // indicates that it is the first player's turn
+        if self.board_size == 0 {
+            // nothing picked a board, e.g. for series games: play the classic one
+            self.board_size = Self::CLASSIC_BOARD_SIZE;
+            self.win_length = Self::CLASSIC_BOARD_SIZE;
+        }
+        self.version = CURRENT_GAME_VERSION;
+        Ok(()) // returns a success value
+    } 
+    // starts a new game and sets up initial state require_eq! is a rust macro that ensure that two values are equal.
+    // In this case, the macro is checking to make sure that the turn field is equal to 0. Otherwise return an error.
+    //
+
+    pub fn is_active(&self) -> bool {
+        self.state == GameState::Active
+    }
+    // checks if a game is active
+    // self is a reference to the Game struct that the function is being called on
+
+    pub fn use_nonce(&mut self, expected_nonce: u64) -> Result<()> {
+        require_eq!(self.nonce, expected_nonce, TicTacToeError::StaleNonce);
+        self.nonce += 1;
+        Ok(())
+    }
+    // clients send the nonce they last saw with every state-changing instruction.
+    // if it doesn't match, the transaction is a retry of one that already landed (or was built from stale state) and is rejected
+
+    pub fn reset(&mut self) {
+        self.turn = 0;
+        self.board = [None; Self::MAX_CELLS];
+        self.state = GameState::Active;
+        self.last_move_tile = None;
+        self.play_count = 0;
+        self.moves = [MoveRecord::EMPTY; Self::MAX_CELLS];
+        self.draw_offered_by = None;
+        self.swap_applied = false;
+        // the next game is finalized and recorded on the profiles of its own
+        self.finalized = false;
+        self.ended_at = 0;
+        self.results_recorded = [false; 2];
+    } // wipes the board so the same account can host another game, start() has to be called afterwards
+
+    fn current_player_index(&self) -> Result<usize> {
+        let turns_played = self.turn.checked_sub(1).ok_or(TicTacToeError::GameNotStarted)?;
+        Ok((turns_played % 2) as usize)
+    } // returns the index of the current player to decide whose turn it is, turn 0 means start() hasn't run yet
+
+    pub fn check_elo_requirement(&self, elo: i32) -> Result<()> {
+        require!(elo >= self.minimum_elo_requirement, TicTacToeError::EloTooLow);
+        if let Some(maximum) = self.maximum_elo_requirement {
+            require!(elo <= maximum, TicTacToeError::EloTooHigh);
+        }
+        Ok(())
+    } // whether a player with this rating may join
+
+    pub fn role_of(&self, player: &Pubkey) -> u8 {
+        self.players.iter().position(|p| p == player).map_or(u8::MAX, |index| index as u8)
+    } // index of the player in players, u8::MAX for anyone else
+
+    pub fn current_player(&self) -> Result<Pubkey> {
+        Ok(self.players[self.current_player_index()?])
+    } // return public key of the current player
+
+    pub fn play(&mut self, tile: &Tile) -> Result<()> {
+        require!(self.is_active(), TicTacToeError::GameAlreadyOver);
+        self.draw_offered_by = None; // playing on rescinds a pending draw offer, from either side
+
+        require!(tile.row < self.board_size && tile.column < self.board_size, TicTacToeError::TileOutOfBounds);
+        let (row, column) = (tile.row as usize, tile.column as usize);
+        require!(self.cell(row, column).is_none(), TicTacToeError::TileAlreadySet);
+        let sign = Sign::from_usize(self.current_player_index()?).unwrap();
+        self.set_cell(row, column, Some(sign));
+        self.moves[self.play_count as usize] = MoveRecord { row: tile.row, column: tile.column, sign };
+
+        self.last_move_tile = Some(*tile);
+        self.play_count += 1;
+
+        self.update_state();
+
+        if GameState::Active == self.state {
+            self.turn = self.turn.checked_add(1).ok_or(TicTacToeError::TurnOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_move_history(&self) -> &[MoveRecord] {
+        &self.moves[..self.play_count as usize]
+    } // the moves played so far, oldest first
+
+    pub fn validate_board_config(board_size: u8, win_length: u8) -> Result<()> {
+        let sizes = Self::CLASSIC_BOARD_SIZE..=Self::MAX_BOARD_SIZE;
+        require!(
+            sizes.contains(&board_size) && sizes.contains(&win_length) && win_length <= board_size,
+            TicTacToeError::InvalidBoardConfig
+        );
+        Ok(())
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> Option<Sign> {
+        self.board[row * self.board_size as usize + column]
+    }
+
+    fn set_cell(&mut self, row: usize, column: usize, cell: Option<Sign>) {
+        self.board[row * self.board_size as usize + column] = cell;
+    }
+
+    pub fn cell_count(&self) -> u8 {
+        self.board_size * self.board_size
+    }
+
+    fn lines(&self) -> impl Iterator<Item = impl Iterator<Item = Option<Sign>> + '_> + '_ {
+        let (size, length) = (self.board_size as isize, self.win_length as isize);
+        Self::DIRECTIONS.into_iter().flat_map(move |(row_step, column_step)| {
+            (0..size * size).filter_map(move |start| {
+                let (row, column) = (start / size, start % size);
+                let (last_row, last_column) = (row + row_step * (length - 1), column + column_step * (length - 1));
+                let fits = last_row < size && (0..size).contains(&last_column);
+                fits.then(move || {
+                    (0..length).map(move |i| self.cell((row + row_step * i) as usize, (column + column_step * i) as usize))
+                })
+            })
+        })
+    } // every run of win_length cells in a row, column or diagonal, so on a 5x5 board with win_length 4 each row holds two
+
+    pub fn valid_moves(&self) -> Vec<Tile> {
+        let mut moves = Vec::new();
+        for row in 0..self.board_size as usize {
+            for column in 0..self.board_size as usize {
+                if self.cell(row, column).is_none() {
+                    moves.push(Tile { row: row as u8, column: column as u8 });
+                }
+            }
+        }
+        moves
+    } // every empty tile, row by row
+
+    pub fn legal_moves_count(&self) -> u8 {
+        self.board[..self.cell_count() as usize].iter().filter(|cell| cell.is_none()).count() as u8
+    } // same as valid_moves().len() without building the Vec
+
+    pub fn is_endgame(&self) -> bool {
+        self.legal_moves_count() <= Self::ENDGAME_MOVES
+    } // hints and AI switch from strategy to tactics here
+
+    fn has_line(&self, sign: Sign) -> bool {
+        self.lines().any(|mut line| line.all(|cell| cell == Some(sign)))
+    }
+
+    pub fn check_winner(&self) -> Option<Sign> {
+        self.lines().find_map(|mut line| {
+            let first = line.next().flatten()?;
+            line.all(|cell| cell == Some(first)).then_some(first)
+        })
+    } // the sign with win_length in a row, column or diagonal, if any
+
+    pub fn find_winning_move(&self, sign: Sign) -> Option<Tile> {
+        self.valid_moves().into_iter().find(|tile| {
+            let mut board = self.clone();
+            board.set_cell(tile.row as usize, tile.column as usize, Some(sign));
+            board.has_line(sign)
+        })
+    } // a tile that completes a line for `sign`, if there is one
+
+    pub fn expected_outcome(&self) -> Option<i8> {
+        let index = self.current_player_index().ok()?; // not started
+        let free = self.legal_moves_count();
+        if self.board_size == Self::CLASSIC_BOARD_SIZE && self.win_length == Self::CLASSIC_BOARD_SIZE && free >= 8 {
+            return Some(0); // every 3x3 opening is drawn with perfect play, no search needed
+        }
+        // the search grows with the factorial of the free tiles, see utils::estimate_search_compute_units
+        if free > utils::MAX_SEARCH_FREE_CELLS {
+            return None;
+        }
+        let mut board = self.clone();
+        Some(board.negamax(Sign::from_usize(index).unwrap()))
+    } // result of perfect play by both sides, seen from the player to move, if the position can be solved
+
+    fn negamax(&mut self, sign: Sign) -> i8 {
+        let opponent = Sign::from_usize(1 - sign.to_usize().unwrap()).unwrap();
+        let mut best = -1;
+        for tile in self.valid_moves() {
+            let (row, column) = (tile.row as usize, tile.column as usize);
+            self.set_cell(row, column, Some(sign));
+            let outcome = if self.has_line(sign) {
+                1
+            } else if self.legal_moves_count() == 0 {
+                0
+            } else {
+                -self.negamax(opponent)
+            };
+            self.set_cell(row, column, None);
+            if outcome == 1 {
+                return 1; // nothing beats a forced win
+            }
+            best = best.max(outcome);
+        }
+        best
+    }
+
+    pub fn is_zugzwang(&self) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        let Ok(index) = self.current_player_index() else {
+            return false;
+        };
+        let sign = Sign::from_usize(index).unwrap();
+        let opponent = Sign::from_usize(1 - index).unwrap();
+        let moves = self.valid_moves();
+        !moves.is_empty()
+            && moves.iter().all(|tile| {
+                let mut board = self.clone();
+                board.set_cell(tile.row as usize, tile.column as usize, Some(sign));
+                !board.has_line(sign) && board.find_winning_move(opponent).is_some()
+            })
+    } // whether every move of the current player hands the opponent an immediate win
+
+    pub fn score(&self, for_sign: Sign) -> i32 {
+        let opponent = Sign::from_usize(1 - for_sign.to_usize().unwrap()).unwrap();
+        if self.has_line(for_sign) {
+            return 100;
+        }
+        if self.has_line(opponent) {
+            return -100;
+        }
+        let one_short = self.win_length as usize - 1;
+        self.lines()
+            .map(|line| {
+                let cells: Vec<Option<Sign>> = line.collect();
+                let own = cells.iter().filter(|cell| **cell == Some(for_sign)).count();
+                let theirs = cells.iter().filter(|cell| **cell == Some(opponent)).count();
+                match (own, theirs) {
+                    (own, 0) if own == one_short => 10,
+                    (0, theirs) if theirs == one_short => -10,
+                    (own, 0) if own > 0 => 1,
+                    (0, theirs) if theirs > 0 => -1,
+                    _ => 0, // blocked or empty lines are worth nothing to either side
+                }
+            })
+            .sum::<i32>()
+            .clamp(-99, 99) // a 5x5 board has enough lines to outscore a win otherwise
+    } // +100 won, -100 lost, otherwise lines one sign short of a win count 10 and other open lines 1, a full board without a line is 0
+
+    pub fn simulate_game(players: [Pubkey; 2], board_size: u8, win_length: u8, moves: &[Tile]) -> Result<Game> {
+        let mut game = Game {
+            players: [Pubkey::default(); 2],
+            turn: 0,
+            board: [None; Game::MAX_CELLS],
+            board_size,
+            win_length,
+            state: GameState::Active,
+            last_move_tile: None,
+            nonce: 0,
+            room: None,
+            invite_expiry: 0,
+            minimum_elo_requirement: 0,
+            maximum_elo_requirement: None,
+            observer_count: 0,
+            finalized: false,
+            swap_applied: false,
+            ended_at: 0,
+            referee: None,
+            dispute_resolution_timeout: 0,
+            time_bank_p1: 0,
+            time_bank_p2: 0,
+            last_move_at: 0,
+            created_via: CreatedVia::Direct,
+            play_count: 0,
+            version: 0,
+            wager: 0,
+            wager_settled: false,
+            resigned: false,
+            turn_deadline_slots: 0,
+            last_move_slot: 0,
+            moves: [MoveRecord::EMPTY; Game::MAX_CELLS],
+            timed_out: false,
+            results_recorded: [false; 2],
+            draw_offered_by: None,
+            time_bank_enabled: false,
+        };
+        game.start(players)?;
+        for tile in moves {
+            game.play(tile)?;
+        }
+        Ok(game)
+    } // replays the moves on a fresh board with the normal play rules
+
+    pub fn random_game(seed: &[u8; 32]) -> Result<(Vec<u8>, Game)> {
+        let mut moves = Vec::new();
+        let classic = Self::CLASSIC_BOARD_SIZE;
+        let mut game = Self::simulate_game([Pubkey::default(); 2], classic, classic, &[])?;
+        while game.is_active() {
+            let options = game.valid_moves();
+            let hash = hashv(&[seed, &[game.turn]]).to_bytes();
+            let pick = u64::from_le_bytes(hash[..8].try_into().unwrap()) as usize % options.len();
+            let tile = &options[pick];
+            game.play(tile)?;
+            moves.push(tile.row * game.board_size + tile.column);
+        }
+        Ok((moves, game))
+    } // both players pick valid_moves()[hash(seed || turn) % len], so a seed always yields the same game
+
+    fn cell_code(cell: Option<Sign>) -> u8 {
+        match cell {
+            None => 0,
+            Some(Sign::X) => 1,
+            Some(Sign::O) => 2,
+        }
+    }
+
+    pub fn first_board_mismatch(&self, expected: &Game) -> Option<(u8, u8, u8, u8)> {
+        for row in 0..self.board_size as usize {
+            for column in 0..self.board_size as usize {
+                let (want, got) = (expected.cell(row, column), self.cell(row, column));
+                if want != got {
+                    return Some((row as u8, column as u8, Self::cell_code(want), Self::cell_code(got)));
+                }
+            }
+        }
+        None
+    } // (row, column, expected, found) of the first differing cell
+
+    pub fn recompute_state(&self) -> GameState {
+        let mut fresh = self.clone();
+        fresh.state = GameState::Active;
+        fresh.update_state();
+        fresh.state
+    } // the state the board implies, worked out on a copy without touching the stored game
+
+    fn sign_counts_alternate(&self) -> bool {
+        let cells = &self.board[..self.cell_count() as usize];
+        let x = cells.iter().filter(|cell| **cell == Some(Sign::X)).count();
+        let o = cells.iter().filter(|cell| **cell == Some(Sign::O)).count();
+        x == o || x == o + 1
+    } // X moves first, so it is never behind O or more than one sign ahead
+
+    fn update_state(&mut self) {
+        let filled = self.cell_count() - self.legal_moves_count();
+        // the shortcut relies on the signs alternating, a board where one sign moved twice gets the full check
+        if filled + 1 < 2 * self.win_length && self.sign_counts_alternate() {
+            return; // X hasn't placed win_length signs yet, a win is impossible
+        }
+        if let Some(sign) = self.check_winner() {
+            self.state = GameState::Won {
+                winner: self.players[sign.to_usize().unwrap()], // players[0] always plays X, also after a swap
+            };
+            return;
+        }
+
+        // reaching this code means the game has not been won,
+        // so it's a tie once there are no free tiles left
+        if self.legal_moves_count() == 0 {
+            self.state = GameState::Tie;
+        }
+    }
+}
+
+impl ProgramConfig {
+    pub const MAXIMUM_SIZE: usize = 32 + ConfigSettings::MAXIMUM_SIZE;
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.authority.key();
+        config.settings = ConfigSettings::DEFAULT;
+        Ok(())
+    }
+}
+
+impl ConfigSettings {
+    pub const MAXIMUM_SIZE: usize = 8 + 8 + 2 + 2 + (4 * 5) + 4 + 4 + 8 + 4 + 4 + 8 + 4;
+
+    pub const DEFAULT: ConfigSettings = ConfigSettings {
+        invite_ttl_secs: 24 * 60 * 60,
+        withdrawal_delay_secs: 3 * 24 * 60 * 60,
+        resign_penalty_bps: 10_000,
+        platform_fee_bps: 0,
+        rank_thresholds: [1000, 1300, 1500, 1700, 2000],
+        max_allowed_abandons: 5,
+        abandon_recovery_games: 10,
+        referee_window_secs: 7 * 24 * 60 * 60,
+        move_time_limit_secs: 60,
+        max_time_bank_accrual_secs: 10,
+        gc_grace_period_secs: 30 * 24 * 60 * 60,
+        max_calls_per_slot: 20,
+    };
+}
+
+impl AbandonRecord {
+    pub const MAXIMUM_SIZE: usize = 4 + 4;
+    pub const WARNING_LEVELS: [u32; 3] = [3, 5, 10];
+
+    pub fn record_abandon(&mut self) -> Option<u32> {
+        self.games_abandoned += 1;
+        self.completed_games_since_last_abandon = 0;
+        Self::WARNING_LEVELS.contains(&self.games_abandoned).then_some(self.games_abandoned)
+    } // returns the abandon count when the player should get a PlayerWarned
+
+    pub fn record_completion(&mut self) {
+        self.completed_games_since_last_abandon += 1;
+    }
+
+    pub fn ensure_allowed(&self, settings: &ConfigSettings) -> Result<()> {
+        require!(
+            self.games_abandoned <= settings.max_allowed_abandons
+                || self.completed_games_since_last_abandon >= settings.abandon_recovery_games,
+            TicTacToeError::TooManyAbandons
+        );
+        Ok(())
+    } // checked by setup_game, serial abandoners have to finish some games first
+}
+
+impl SeasonalRank {
+    pub fn from_elo(elo: i32, thresholds: &[i32; 5]) -> SeasonalRank {
+        match thresholds.iter().rposition(|threshold| elo >= *threshold) {
+            None => SeasonalRank::Unranked,
+            Some(0) => SeasonalRank::Bronze,
+            Some(1) => SeasonalRank::Silver,
+            Some(2) => SeasonalRank::Gold,
+            Some(3) => SeasonalRank::Platinum,
+            Some(_) => SeasonalRank::Diamond,
+        }
+    } // the highest rank whose threshold the rating reaches
+
+    pub fn ensure_ranked_eligible(elo: i32, thresholds: &[i32; 5]) -> Result<()> {
+        require!(Self::from_elo(elo, thresholds) >= SeasonalRank::Silver, TicTacToeError::EloTooLowForRanked);
+        Ok(())
+    } // ranked tournament games are for Silver and up
+}
+
+impl PendingWithdrawal {
+    pub const MAXIMUM_SIZE: usize = 8 + 32 + 8;
+
+    pub fn initiate_withdrawal(ctx: Context<InitiateWithdrawal>, amount: u64, destination: Pubkey) -> Result<()> {
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        pending.amount = amount;
+        pending.destination = destination;
+        pending.unlocks_at = Clock::get()?.unix_timestamp + ctx.accounts.config.settings.withdrawal_delay_secs;
+        Ok(())
+    }
+
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        let pending = &ctx.accounts.pending_withdrawal;
+        require!(Clock::get()?.unix_timestamp >= pending.unlocks_at, TicTacToeError::WithdrawalLocked);
+
+        let bump = *ctx.bumps.get("treasury").unwrap();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"treasury", &[bump]]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            pending.amount,
+        )
+    } // the pending withdrawal is closed by the `close` constraint on ExecuteWithdrawal
+}
+
+impl GameRoom {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + (1 + 1 + 1) + 4 + 2 + 4 + 4 + 1 + 1 + 8 + (1 + 32);
+    pub const DEFAULT_MIN_GAMES_BEFORE_RANKING: u8 = 5;
+
+    pub fn create_room(
+        ctx: Context<CreateRoom>,
+        _room_id: u8,
+        name: [u8; 32],
+        config: GameRoomConfig,
+        min_elo: i32,
+        elo_cap: i32,
+        rating_decay_rate: u8,
+    ) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.creator.key(), ctx.remaining_accounts)?;
+        Game::validate_board_config(config.board_size, config.win_length)?;
+        require!(min_elo <= elo_cap, TicTacToeError::RoomEloMismatch);
+        require!(rating_decay_rate <= 100, TicTacToeError::InvalidDecayRate);
+
+        let room = &mut ctx.accounts.room;
+        room.name = name;
+        room.creator = ctx.accounts.creator.key();
+        room.config = config;
+        room.member_count = 0;
+        room.active_games = 0;
+        room.min_elo = min_elo;
+        room.elo_cap = elo_cap;
+        room.min_games_before_ranking = Self::DEFAULT_MIN_GAMES_BEFORE_RANKING;
+        room.rating_decay_rate = rating_decay_rate;
+        room.last_decay_slot = 0;
+        room.featured_game = None;
+        Ok(())
+    }
+
+    pub fn set_featured_game(ctx: Context<SetFeaturedGame>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.creator.key(), ctx.remaining_accounts)?;
+        require!(ctx.accounts.game.is_active(), TicTacToeError::GameAlreadyOver);
+        let room = &mut ctx.accounts.room;
+        room.featured_game = Some(ctx.accounts.game.key()); // replaces the previous one, a room features one game at a time
+        emit!(FeaturedGameSet { room: room.key(), game: ctx.accounts.game.key() });
+        Ok(())
+    }
+
+    pub fn check_elo(&self, elo: i32) -> Result<()> {
+        require!((self.min_elo..=self.elo_cap).contains(&elo), TicTacToeError::RoomEloMismatch);
+        Ok(())
+    } // checked when a player joins the room and again for every game they set up or join in it
+
+    fn release_game(&mut self, game: Pubkey) -> bool {
+        self.active_games -= 1;
+        let was_featured = self.featured_game == Some(game);
+        if was_featured {
+            self.featured_game = None;
+        }
+        was_featured
+    } // a game of the room was finalized, cancelled or garbage collected, true if that cleared the featured game
+
+    pub fn apply_rating_decay<'info>(ctx: Context<'_, '_, '_, 'info, ApplyRatingDecay<'info>>) -> Result<()> {
+        let room = &mut ctx.accounts.room;
+        let slot = Clock::get()?.slot;
+        require!(
+            room.last_decay_slot == 0 || room.last_decay_slot + SLOTS_PER_WEEK <= slot,
+            TicTacToeError::DecayTooSoon
+        );
+        room.last_decay_slot = slot;
+
+        let mut decayed: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts {
+            let mut member = Account::<RoomMember>::try_from(info)?;
+            require_keys_eq!(member.room, room.key(), TicTacToeError::RoomAccountMismatch);
+            if decayed.contains(&info.key()) {
+                continue; // each member decays once per week, no matter how often it's passed
+            }
+            if member.decay(room.rating_decay_rate, slot) {
+                decayed.push(info.key());
+                member.exit(&crate::ID)?;
+            }
+        }
+        emit!(RatingDecayApplied { room: room.key(), players_affected: decayed.len() as u16 });
+        Ok(())
+    } // permissionless, once a week
+
+    pub fn join_room(ctx: Context<JoinRoom>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        ctx.accounts.room.check_elo(PlayerProfile::rating_of(&ctx.accounts.profile))?;
+        let member = &mut ctx.accounts.member;
+        member.room = ctx.accounts.room.key();
+        member.player = ctx.accounts.player.key();
+        member.joined_at = Clock::get()?.unix_timestamp;
+        member.calibration_remaining = ctx.accounts.room.min_games_before_ranking;
+        member.unranked = member.calibration_remaining > 0;
+        member.elo_rating = STARTING_ELO;
+        member.last_active_slot = Clock::get()?.slot;
+
+        let room = &mut ctx.accounts.room;
+        room.member_count += 1;
+        Ok(())
+    }
+}
+
+impl RoomMember {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 8 + 1 + 1 + 4 + 8;
+
+    pub fn decay(&mut self, rate: u8, slot: u64) -> bool {
+        if rate == 0 || self.last_active_slot + SLOTS_PER_WEEK >= slot {
+            return false;
+        }
+        self.elo_rating -= self.elo_rating * rate as i32 / 100;
+        true
+    } // takes `rate` percent off the rating of a member who hasn't played for a week
+
+    pub fn record_calibration_game(&mut self) -> bool {
+        if self.calibration_remaining == 0 {
+            return false;
+        }
+        self.calibration_remaining -= 1;
+        self.unranked = self.calibration_remaining > 0;
+        !self.unranked
+    } // true once the last calibration game is done
+}
+
+impl GameObserver {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 32;
+    pub const MAXIMUM_PER_GAME: u8 = 3;
+
+    pub fn register_game_observer(ctx: Context<RegisterGameObserver>, program_id: Pubkey) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.registered_by.key(), ctx.remaining_accounts)?;
+        let game = &mut ctx.accounts.game;
+        // observers are called from finalize_game, so only the two players decide which programs those are
+        require!(game.role_of(&ctx.accounts.registered_by.key()) != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(!game.finalized, TicTacToeError::GameAlreadyFinalized);
+        require!(game.observer_count < Self::MAXIMUM_PER_GAME, TicTacToeError::TooManyObservers);
+        game.observer_count += 1;
+
+        let observer = &mut ctx.accounts.observer;
+        observer.observer = program_id;
+        observer.game = game.key();
+        observer.registered_by = ctx.accounts.registered_by.key();
+        Ok(())
+    }
+
+    /// calls `on_game_complete(game: Pubkey, winner: Option<Pubkey>)` on the observer program, the Anchor
+    /// interface every observer implements. It gets the game (read-only) and its own state account (writable).
+    /// finalize_game reports an error from here with ObserverNotificationFailed instead of failing.
+    fn notify<'info>(
+        program: &AccountInfo<'info>,
+        game: &AccountInfo<'info>,
+        state: &AccountInfo<'info>,
+        winner: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(program.executable, TicTacToeError::ObserverAccountMismatch);
+        let mut data = anchor_lang::solana_program::hash::hash(b"global:on_game_complete").to_bytes()[..8].to_vec();
+        (game.key(), winner).serialize(&mut data)?;
+        let instruction = Instruction {
+            program_id: program.key(),
+            accounts: vec![AccountMeta::new_readonly(game.key(), false), AccountMeta::new(state.key(), false)],
+            data,
+        };
+        invoke(&instruction, &[game.clone(), state.clone(), program.clone()])?;
+        Ok(())
+    }
+}
+
+impl GameSetupParams {
+    pub const MAXIMUM_SIZE: usize = 4 + (1 + 4) + (1 + 32) + 4 + 1 + 8 + 1 + 1;
+}
+
+impl GameInvitation {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + GameSetupParams::MAXIMUM_SIZE + 8;
+
+    pub fn invite_to_game(ctx: Context<InviteToGame>, to: Pubkey, game_config: GameSetupParams) -> Result<()> {
+        let from = ctx.accounts.from.key();
+        ensure_not_banned(&from, ctx.remaining_accounts)?;
+        require!(ctx.accounts.friends.contains(&to), TicTacToeError::NotFriends);
+
+        let invitation = &mut ctx.accounts.invitation;
+        invitation.from = from;
+        invitation.to = to;
+        invitation.game_config = game_config;
+        invitation.expires_at = Clock::get()?.unix_timestamp + ctx.accounts.config.settings.invite_ttl_secs;
+        Ok(())
+    }
+
+    pub fn accept_invitation(ctx: Context<AcceptInvitation>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.to.key(), ctx.remaining_accounts)?;
+        let invitation = &ctx.accounts.invitation;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= invitation.expires_at, TicTacToeError::InvitationExpired);
+
+        ctx.accounts.stats.record_game_created(CreatedVia::Direct);
+        let game = &mut ctx.accounts.game;
+        game.created_via = CreatedVia::Direct;
+        let clock = Clock::get()?;
+        game.init([invitation.from, invitation.to], &invitation.game_config, &ctx.accounts.config.settings, &clock)?;
+        Game::emit_started(game, &clock);
+        Ok(())
+    } // sets the game up as if `from` had called setup_game, the invitation's rent goes back to `from`
+}
+
+impl PlayerProfile {
+    pub const MAXIMUM_SIZE: usize = 32 + 4 + 4 + 4 + 4 + AbandonRecord::MAXIMUM_SIZE + 4 + 1 + 4 + 4;
+
+    pub fn record_result(ctx: Context<RecordResult>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(!game.is_active(), TicTacToeError::GameNotOver);
+        let infos = [ctx.accounts.profile_one.to_account_info(), ctx.accounts.profile_two.to_account_info()];
+        let ratings = [Self::rating_of(&infos[0]), Self::rating_of(&infos[1])]; // both from before the game is counted
+        let mut recorded = false;
+        for (index, info) in infos.iter().enumerate() {
+            let Ok(mut profile) = Account::<PlayerProfile>::try_from(info) else { continue }; // no profile to count on
+            if game.results_recorded[index] {
+                continue;
+            }
+            game.results_recorded[index] = true;
+            recorded = true;
+            if let Some(abandons) = profile.record(game, ratings[1 - index]) {
+                emit!(PlayerWarned { player: profile.owner, abandons });
+            }
+            if let Some((old, new)) = profile.update_rank(&ctx.accounts.config.settings.rank_thresholds) {
+                emit!(RankChanged { player: profile.owner, old, new });
+            }
+            profile.exit(&crate::ID)?;
+        }
+        require!(recorded, TicTacToeError::ResultAlreadyRecorded);
+        Ok(())
+    }
+
+    pub fn record_tournament_final(&mut self, winner: Pubkey) {
+        if self.owner == winner {
+            self.tournament_wins += 1;
+        } else {
+            self.tournament_runner_up += 1;
+        }
+    }
+
+    pub fn rating_of(info: &AccountInfo) -> i32 {
+        Account::<PlayerProfile>::try_from(info).map_or(STARTING_ELO, |profile| profile.elo_rating)
+    } // the rating behind a [b"profile", player] PDA, players without a profile count as newcomers
+
+    pub fn record(&mut self, game: &Game, opponent_rating: i32) -> Option<u32> {
+        self.games_played += 1;
+        let score_bps = match game.state {
+            GameState::Won { winner } if winner == self.owner => {
+                self.wins += 1;
+                Some(10_000)
+            }
+            GameState::Won { .. } => {
+                self.losses += 1;
+                Some(0)
+            }
+            GameState::Tie => {
+                self.draws += 1;
+                Some(5_000)
+            }
+            GameState::Active | GameState::Cancelled { .. } => {
+                self.draws += 1;
+                None // nobody played it out, so it isn't rated
+            }
+        };
+        if let Some(score_bps) = score_bps {
+            self.elo_rating += utils::compute_elo_change(self.elo_rating, opponent_rating, score_bps);
+        }
+        if game.timed_out && score_bps == Some(0) {
+            return self.abandons.record_abandon();
+        }
+        self.abandons.record_completion();
+        None
+    } // counts a finished game and rates it, returns the abandon count when the player should be warned
+
+    pub fn update_rank(&mut self, thresholds: &[i32; 5]) -> Option<(SeasonalRank, SeasonalRank)> {
+        let old = self.seasonal_rank;
+        self.seasonal_rank = SeasonalRank::from_elo(self.elo_rating, thresholds);
+        (self.seasonal_rank != old).then_some((old, self.seasonal_rank))
+    } // (old, new) when the current rating puts the player into another rank
+}
+
+impl FriendsList {
+    pub const MAXIMUM_FRIENDS: usize = 50;
+    pub const MAXIMUM_SIZE: usize = 32 + (32 * Self::MAXIMUM_FRIENDS);
+
+    pub fn contains(&self, player: &Pubkey) -> bool {
+        *player != Pubkey::default() && self.friends.contains(player)
+    }
+
+    pub fn add(&mut self, friend: Pubkey) -> Result<()> {
+        require!(friend != Pubkey::default() && friend != self.owner, TicTacToeError::InvalidFriend);
+        require!(!self.contains(&friend), TicTacToeError::AlreadyFriends);
+        let slot = self.friends.iter_mut().find(|slot| **slot == Pubkey::default());
+        *slot.ok_or(TicTacToeError::FriendsListFull)? = friend;
+        Ok(())
+    } // fills the first empty slot
+
+    pub fn remove(&mut self, friend: &Pubkey) -> Result<()> {
+        require!(self.contains(friend), TicTacToeError::NotFriends);
+        let slot = self.friends.iter_mut().find(|slot| *slot == friend).unwrap();
+        *slot = Pubkey::default();
+        Ok(())
+    }
+
+    pub fn mutual(&self, other: &FriendsList) -> Vec<Pubkey> {
+        self.friends.iter().filter(|friend| other.contains(friend)).copied().collect()
+    }
+}
+
+impl GlobalStats {
+    pub const MAXIMUM_SIZE: usize = 8 + 8 + 8 + (8 * 6);
+
+    pub fn record_game_created(&mut self, via: CreatedVia) {
+        self.games_by_origin[via as usize] += 1;
+    }
+}
+
+impl Tournament {
+    pub const MAXIMUM_SIZE: usize = 32 + (32 * 8) + 1 + 1 + (32 * 7) + (32 * 7) + 1 + (1 + 32) + (4 + 2 * 8) + 1;
+
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        capacity: u8,
+        prize_shares: Vec<u16>,
+        ranked: bool,
+    ) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.organizer.key(), ctx.remaining_accounts)?;
+        require!(capacity == 4 || capacity == 8, TicTacToeError::InvalidParticipantCount);
+        if !prize_shares.is_empty() {
+            require!(prize_shares.len() <= capacity as usize, TicTacToeError::InvalidPrizeShares);
+            utils::compute_prize_distribution(0, &prize_shares)?; // checks that the shares add up
+        }
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.organizer = ctx.accounts.organizer.key();
+        tournament.capacity = capacity;
+        tournament.prize_shares = prize_shares;
+        tournament.ranked = ranked;
+        Ok(())
+    }
+
+    pub fn register_player(ctx: Context<RegisterPlayer>) -> Result<()> {
+        let player = ctx.accounts.player.key();
+        ensure_not_banned(&player, ctx.remaining_accounts)?;
+        if ctx.accounts.tournament.ranked {
+            let rating = PlayerProfile::rating_of(&ctx.accounts.profile.to_account_info());
+            SeasonalRank::ensure_ranked_eligible(rating, &ctx.accounts.config.settings.rank_thresholds)?;
+        }
+        ctx.accounts.tournament.register(player)
+    }
+
+    pub fn register(&mut self, player: Pubkey) -> Result<()> {
+        require_eq!(self.round, 0, TicTacToeError::TournamentAlreadyStarted);
+        let count = self.participant_count as usize;
+        require!(count < self.capacity as usize, TicTacToeError::TournamentFull);
+        require!(!self.participants[..count].contains(&player), TicTacToeError::AlreadyRegistered);
+        self.participants[count] = player;
+        self.participant_count += 1;
+        Ok(())
+    }
+
+    pub fn round_slots(&self, round: u8) -> std::ops::Range<usize> {
+        let (mut start, mut games) = (0, self.participant_count as usize / 2);
+        for _ in 1..round {
+            start += games;
+            games /= 2;
+        }
+        start..start + games
+    } // slots in games / winners the given round, starting at 1, plays in
+
+    pub fn round_complete(&self) -> bool {
+        self.winners[self.round_slots(self.round)].iter().all(|winner| *winner != Pubkey::default())
+    }
+
+    pub fn next_round_pairs(&self) -> Result<Vec<[Pubkey; 2]>> {
+        require!(self.round_complete(), TicTacToeError::RoundNotComplete);
+        Ok(self.winners[self.round_slots(self.round)].chunks_exact(2).map(|pair| [pair[0], pair[1]]).collect())
+    } // winners of neighbouring games meet in the next round, empty after the final
+
+    pub fn slot_players(&self, slot: usize) -> [Pubkey; 2] {
+        let first_round = self.round_slots(1);
+        if first_round.contains(&slot) {
+            return [self.participants[2 * slot], self.participants[2 * slot + 1]];
+        }
+        let (mut previous, mut round) = (first_round, 2);
+        while !self.round_slots(round).contains(&slot) {
+            previous = self.round_slots(round);
+            round += 1;
+        }
+        let feeder = previous.start + 2 * (slot - self.round_slots(round).start);
+        [self.winners[feeder], self.winners[feeder + 1]]
+    } // who the bracket sends into the game in `slot`, in the order start_tournament / advance_winner set it up
+
+    pub fn placements(&self) -> Vec<Pubkey> {
+        let Some(champion) = self.winner else {
+            return Vec::new();
+        };
+        let mut placements = vec![champion];
+        for round in (1..=self.round).rev() {
+            for slot in self.round_slots(round) {
+                let [one, two] = self.slot_players(slot);
+                placements.push(if self.winners[slot] == one { two } else { one });
+            }
+        }
+        placements
+    } // the champion, then the losers of each round from the final back, so players knocked out together follow bracket order
+
+    pub fn slot_matches(&self, slot: usize, game: &Game) -> bool {
+        let [one, two] = self.slot_players(slot);
+        // a tied game is replayed with the signs swapped, so the players may come in either order
+        let players_match = game.players == [one, two] || game.players == [two, one];
+        let winner_matches = match (self.winners[slot], &game.state) {
+            (recorded, _) if recorded == Pubkey::default() => true, // advance_winner hasn't seen the game yet
+            (recorded, GameState::Won { winner }) => recorded == *winner,
+            _ => false,
+        };
+        players_match && winner_matches && game.created_via == CreatedVia::Tournament
+    } // the game was set up for the players the bracket sends there, and its winner is the one recorded
+
+    pub fn verify_tournament_bracket(ctx: Context<VerifyTournamentBracket>) -> Result<()> {
+        let tournament = &ctx.accounts.tournament;
+        let tournament_key = tournament.key();
+        let slots: Vec<usize> =
+            (0..tournament.games.len()).filter(|slot| tournament.games[*slot] != Pubkey::default()).collect();
+        require_eq!(ctx.remaining_accounts.len(), slots.len(), TicTacToeError::InvalidTournamentGame);
+        let mut verified = true;
+        for (slot, info) in slots.into_iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(info.key(), tournament.games[slot], TicTacToeError::InvalidTournamentGame);
+            // Game has no tournament field, the [b"tournament_game", tournament, slot] address is what ties it to one
+            let seeds: &[&[u8]] = &[b"tournament_game", tournament_key.as_ref(), &[slot as u8]];
+            let (address, _) = Pubkey::find_program_address(seeds, &crate::ID);
+            let intact = match Account::<Game>::try_from(info) {
+                Ok(game) => info.key() == address && tournament.slot_matches(slot, &game),
+                Err(_) => false, // closed, or not a game anymore
+            };
+            if !intact {
+                verified = false;
+                emit!(BracketIntegrityViolation { tournament: tournament_key, slot_index: slot as u8 });
+            }
+        }
+        if verified {
+            emit!(BracketVerified { tournament: tournament_key });
+        }
+        Ok(())
+    } // permissionless fraud proof, reports every slot that doesn't hold up instead of stopping at the first
+
+    pub fn start_tournament<'info>(ctx: Context<'_, '_, '_, 'info, StartTournament<'info>>) -> Result<()> {
+        let games = Self::split_ban_check(&ctx.accounts.organizer.key(), ctx.remaining_accounts)?;
+        let tournament = &mut ctx.accounts.tournament;
+        require_eq!(tournament.round, 0, TicTacToeError::TournamentAlreadyStarted);
+        // a bracket without byes needs a power of two, capacity keeps it at 4 or 8
+        let count = tournament.participant_count;
+        require!(count >= 4 && count.is_power_of_two(), TicTacToeError::InvalidParticipantCount);
+        tournament.round = 1;
+
+        let pairs: Vec<[Pubkey; 2]> = tournament.participants[..count as usize].chunks_exact(2).map(|pair| [pair[0], pair[1]]).collect();
+        let slots = tournament.round_slots(1);
+        require_eq!(games.len(), slots.len(), TicTacToeError::InvalidTournamentGame);
+        let clock = Clock::get()?;
+        for ((slot, players), info) in slots.zip(pairs).zip(games) {
+            Self::create_game(
+                &ctx.accounts.tournament,
+                slot as u8,
+                info,
+                &ctx.accounts.organizer,
+                &ctx.accounts.system_program,
+                players,
+                &ctx.accounts.config.settings,
+                &clock,
+            )?;
+            ctx.accounts.tournament.games[slot] = info.key();
+            ctx.accounts.stats.record_game_created(CreatedVia::Tournament);
+        }
+        Ok(())
+    }
+
+    pub fn advance_winner<'info>(ctx: Context<'_, '_, '_, 'info, AdvanceWinner<'info>>) -> Result<()> {
+        let games = Self::split_ban_check(&ctx.accounts.payer.key(), ctx.remaining_accounts)?;
+        let tournament = &mut ctx.accounts.tournament;
+        require!(tournament.round > 0 && tournament.winner.is_none(), TicTacToeError::TournamentNotRunning);
+        let game = &mut ctx.accounts.game;
+        let slot = tournament
+            .round_slots(tournament.round)
+            .find(|slot| tournament.games[*slot] == game.key())
+            .ok_or(TicTacToeError::InvalidTournamentGame)?;
+        require!(tournament.winners[slot] == Pubkey::default(), TicTacToeError::WinnerAlreadyAdvanced);
+        match game.state {
+            GameState::Won { winner } => tournament.winners[slot] = winner,
+            GameState::Tie => {
+                // somebody has to go through, so a tied match is replayed on the same account with the signs swapped
+                let players = [game.players[1], game.players[0]];
+                let clock = Clock::get()?;
+                game.reset();
+                game.start(players)?;
+                game.last_move_at = clock.unix_timestamp;
+                game.last_move_slot = clock.slot;
+                Game::emit_started(game, &clock);
+                return Ok(());
+            }
+            _ => return err!(TicTacToeError::GameNotOver),
+        }
+        if !tournament.round_complete() {
+            return Ok(());
+        }
+
+        let pairs = tournament.next_round_pairs()?;
+        if pairs.is_empty() {
+            let winner = tournament.winners[slot];
+            tournament.winner = Some(winner);
+            emit!(TournamentWon { tournament: tournament.key(), winner });
+            for info in [ctx.accounts.profile_one.to_account_info(), ctx.accounts.profile_two.to_account_info()] {
+                let Ok(mut profile) = Account::<PlayerProfile>::try_from(&info) else { continue }; // no profile to count on
+                profile.record_tournament_final(winner);
+                profile.exit(&crate::ID)?;
+            }
+            return Ok(());
+        }
+        tournament.round += 1;
+        let slots = tournament.round_slots(tournament.round);
+        require_eq!(games.len(), slots.len(), TicTacToeError::InvalidTournamentGame);
+        let clock = Clock::get()?;
+        for ((slot, players), info) in slots.zip(pairs).zip(games) {
+            Self::create_game(
+                &ctx.accounts.tournament,
+                slot as u8,
+                info,
+                &ctx.accounts.payer,
+                &ctx.accounts.system_program,
+                players,
+                &ctx.accounts.config.settings,
+                &clock,
+            )?;
+            ctx.accounts.tournament.games[slot] = info.key();
+            ctx.accounts.stats.record_game_created(CreatedVia::Tournament);
+        }
+        Ok(())
+    } // permissionless, the last game of a round sets up the next one
+
+    fn split_ban_check<'a, 'info>(
+        signer: &Pubkey,
+        remaining_accounts: &'a [AccountInfo<'info>],
+    ) -> Result<&'a [AccountInfo<'info>]> {
+        let (ban_info, games) = remaining_accounts.split_first().ok_or(TicTacToeError::BanCheckAccountMissing)?;
+        ensure_not_banned(signer, std::slice::from_ref(ban_info))?;
+        Ok(games)
+    } // the signer's ban PDA comes first in remaining_accounts, the game PDAs follow
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_game<'info>(
+        tournament: &Account<'info, Tournament>,
+        slot: u8,
+        info: &AccountInfo<'info>,
+        payer: &Signer<'info>,
+        system_program: &Program<'info, System>,
+        players: [Pubkey; 2],
+        settings: &ConfigSettings,
+        clock: &Clock,
+    ) -> Result<()> {
+        let tournament_key = tournament.key();
+        let (address, bump) =
+            Pubkey::find_program_address(&[b"tournament_game", tournament_key.as_ref(), &[slot]], &crate::ID);
+        require_keys_eq!(info.key(), address, TicTacToeError::InvalidTournamentGame);
+        let space = 8 + Game::MAXIMUM_SIZE;
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount { from: payer.to_account_info(), to: info.clone() },
+                &[&[b"tournament_game", tournament_key.as_ref(), &[slot], &[bump]]],
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        )?;
+        info.try_borrow_mut_data()?[..8].copy_from_slice(&<Game as anchor_lang::Discriminator>::DISCRIMINATOR);
+
+        let mut game = Account::<Game>::try_from(info)?; // the zeroed data reads as a fresh game
+        let params = GameSetupParams {
+            minimum_elo_requirement: 0,
+            maximum_elo_requirement: None,
+            referee: None,
+            initial_time_bank_secs: 0,
+            friends_only: false,
+            turn_deadline_slots: 0,
+            board_size: Game::CLASSIC_BOARD_SIZE,
+            win_length: Game::CLASSIC_BOARD_SIZE,
+        };
+        game.created_via = CreatedVia::Tournament;
+        game.init(players, &params, settings, clock)?;
+        Game::emit_started(&game, clock);
+        game.exit(&crate::ID)
+    } // the game PDA sits at [b"tournament_game", tournament, slot], so nobody can slip in a game of their own
+}
+
+impl PrizeDistribution {
+    pub const MAXIMUM_SIZE: usize = 32 + 4 + (8 * (32 + 8 + 1));
+
+    pub fn compute_prize_distribution(ctx: Context<ComputePrizeDistribution>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.payer.key(), ctx.remaining_accounts)?;
+        let tournament = &ctx.accounts.tournament;
+        require!(tournament.winner.is_some(), TicTacToeError::TournamentNotFinished);
+        // the pool keeps its own rent, a system account can't be left with less than that unless it's emptied
+        let total = ctx.accounts.prize_pool.lamports().saturating_sub(Rent::get()?.minimum_balance(0));
+        let amounts = utils::compute_prize_distribution(total, &tournament.prize_shares)?;
+
+        let distribution = &mut ctx.accounts.distribution;
+        distribution.tournament = tournament.key();
+        distribution.entries = tournament
+            .placements()
+            .into_iter()
+            .zip(amounts)
+            .map(|(player, amount)| PrizeEntry { player, amount, claimed: false })
+            .collect();
+        emit!(PrizeDistributionComputed { tournament: tournament.key(), total });
+        Ok(())
+    }
+
+    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+        let player = ctx.accounts.player.key();
+        ensure_not_banned(&player, ctx.remaining_accounts)?;
+        let entry = ctx.accounts.distribution.entries.iter_mut().find(|entry| entry.player == player);
+        let entry = entry.ok_or(TicTacToeError::NotAPlayer)?; // nothing to claim without a placement
+        require!(!entry.claimed, TicTacToeError::PrizeAlreadyClaimed);
+        entry.claimed = true;
+        let amount = entry.amount;
+
+        let tournament = ctx.accounts.tournament.key();
+        let bump = *ctx.bumps.get("prize_pool").unwrap();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"prize_pool", tournament.as_ref(), &[bump]]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.prize_pool.to_account_info(),
+                    to: ctx.accounts.player.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )
+    } // each placed player takes their share whenever they like
+}
+
+impl GameLobby {
+    pub const CAPACITY: usize = 32;
+    pub const MAXIMUM_SIZE: usize = (32 * Self::CAPACITY) + 1;
+
+    pub fn add_game(&mut self, game: Pubkey) -> Result<()> {
+        let count = self.count as usize;
+        require!(count < Self::CAPACITY, TicTacToeError::GameLobbyFull);
+        self.pending_games[count] = game;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn remove_game(&mut self, game: &Pubkey) -> bool {
+        let count = self.count as usize;
+        let Some(index) = self.pending_games[..count].iter().position(|pending| pending == game) else {
+            return false;
+        };
+        self.pending_games[index] = self.pending_games[count - 1]; // swap-remove keeps the used entries at the front
+        self.pending_games[count - 1] = Pubkey::default();
+        self.count -= 1;
+        true
+    }
+
+    pub fn pending(&self) -> &[Pubkey] {
+        &self.pending_games[..self.count as usize]
+    }
+
+    pub fn prune_lobby(ctx: Context<PruneLobby>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        for info in ctx.remaining_accounts {
+            let game = Account::<Game>::try_from(info)?;
+            if game.invite_expiry < now {
+                ctx.accounts.lobby.remove_game(&info.key()); // nobody can join anymore, player_one may still cancel
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InstructionRateLimit {
+    pub const MAXIMUM_SIZE: usize = 1 + 4 + 8;
+    pub const ANALYZE_THREATS: u8 = 0;
+    pub const EXPECTED_OUTCOME: u8 = 1;
+
+    pub fn record_call(&mut self, slot: u64, settings: &ConfigSettings) -> Result<()> {
+        if self.slot < slot {
+            self.slot = slot;
+            self.calls_this_slot = 0;
+        }
+        require!(self.calls_this_slot < settings.max_calls_per_slot, TicTacToeError::GlobalRateLimitExceeded);
+        self.calls_this_slot += 1;
+        Ok(())
+    } // counts across all callers, unlike the per-wallet limits
+}
+
+impl PostGameSurvey {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 1 + 1 + 1;
+    pub const WINDOW_SECS: i64 = 48 * 60 * 60;
+
+    pub fn submit_survey(ctx: Context<SubmitSurvey>, enjoyment: u8, fairness: u8, would_play_again: bool) -> Result<()> {
+        let respondent = ctx.accounts.respondent.key();
+        ensure_not_banned(&respondent, ctx.remaining_accounts)?;
+        let game = &ctx.accounts.game;
+        require!(game.role_of(&respondent) != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(game.finalized, TicTacToeError::GameNotOver);
+        require!(
+            Clock::get()?.unix_timestamp <= game.ended_at + Self::WINDOW_SECS,
+            TicTacToeError::SurveyWindowClosed
+        );
+        require!((1..=5).contains(&enjoyment) && (1..=5).contains(&fairness), TicTacToeError::InvalidSurveyScore);
+
+        let survey = &mut ctx.accounts.survey;
+        survey.game = game.key();
+        survey.respondent = respondent;
+        survey.enjoyment = enjoyment;
+        survey.fairness = fairness;
+        survey.would_play_again = would_play_again;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.survey_count += 1;
+        stats.enjoyment_sum += enjoyment as u64;
+        stats.fairness_sum += fairness as u64;
+        Ok(())
+    } // the survey PDA can only be created once, so each player answers once per game
+}
+
+impl DailyLeaderboard {
+    pub const MAXIMUM_ENTRIES: usize = 20;
+    pub const MAXIMUM_SIZE: usize = 4 + 4 + (Self::MAXIMUM_ENTRIES * (32 + 8 + 1));
+    const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+    pub fn frozen_at(&self) -> i64 {
+        (self.date as i64 + 2) * Self::SECONDS_PER_DAY
+    } // 24 hours after the challenge day is over
+
+    pub fn insert(&mut self, entry: DailyEntry, now: i64) -> Result<()> {
+        require!(now < self.frozen_at(), TicTacToeError::LeaderboardFrozen);
+        if self.entries.iter().any(|existing| existing.solver == entry.solver) {
+            return Ok(()); // only a player's first solution counts
+        }
+        self.entries.push(entry);
+        self.entries.sort_by_key(|entry| (entry.solved_at, entry.attempts_used));
+        self.entries.truncate(Self::MAXIMUM_ENTRIES);
+        Ok(())
+    }
+
+    pub fn puzzle(date: u32) -> Result<Game> {
+        let size = Game::CLASSIC_BOARD_SIZE;
+        let mut seed = hashv(&[b"daily_challenge", &date.to_le_bytes()]).to_bytes();
+        loop {
+            let (moves, game) = Game::random_game(&seed)?;
+            if let GameState::Won { .. } = game.state {
+                let tiles: Vec<Tile> = moves[..moves.len() - 1]
+                    .iter()
+                    .map(|cell| Tile { row: cell / size, column: cell % size })
+                    .collect();
+                return Game::simulate_game([Pubkey::default(); 2], size, size, &tiles);
+            }
+            seed = hashv(&[&seed]).to_bytes();
+        }
+    } // the day's challenge is to find the winning move of the first random game for the date that doesn't end in a tie
+
+    pub fn submit_daily_solution(ctx: Context<SubmitDailySolution>, tile: Tile) -> Result<()> {
+        let solver = ctx.accounts.solver.key();
+        ensure_not_banned(&solver, ctx.remaining_accounts)?;
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= leaderboard.date as i64 * Self::SECONDS_PER_DAY, TicTacToeError::ChallengeNotStarted);
+        require!(now < leaderboard.frozen_at(), TicTacToeError::LeaderboardFrozen);
+        let attempts = &mut ctx.accounts.attempts;
+        attempts.attempts = attempts.attempts.saturating_add(1);
+
+        let mut puzzle = Self::puzzle(leaderboard.date)?;
+        let solved = puzzle.play(&tile).is_ok() && matches!(puzzle.state, GameState::Won { .. });
+        if solved {
+            leaderboard.insert(DailyEntry { solver, solved_at: now, attempts_used: attempts.attempts }, now)?;
+        }
+        emit!(DailyChallengeAttempted { date: leaderboard.date, solver, attempts_used: attempts.attempts, solved });
+        Ok(())
+    } // a wrong answer isn't an error, so that the attempt is kept
+}
+
+impl DailyAttempts {
+    pub const MAXIMUM_SIZE: usize = 1;
+}
+
+impl GameHistory {
+    pub const MAXIMUM_MOVES: usize = Game::MAX_CELLS;
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 4 + (Self::MAXIMUM_MOVES * 2);
+
+    pub fn create_history(ctx: Context<CreateHistory>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.recorder.key(), ctx.remaining_accounts)?;
+        let history = &mut ctx.accounts.history;
+        history.game = ctx.accounts.game.key();
+        history.recorder = ctx.accounts.recorder.key();
+        Ok(())
+    }
+
+    pub fn record_move(ctx: Context<RecordMove>, tile: Tile) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.recorder.key(), ctx.remaining_accounts)?;
+        let history = &mut ctx.accounts.history;
+        require!(history.moves.len() < Self::MAXIMUM_MOVES, TicTacToeError::HistoryFull);
+        history.moves.push(tile);
+        Ok(())
+    }
+
+    pub fn reconstruct_board(ctx: Context<ReconstructBoard>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let replay = Game::simulate_game(game.players, game.board_size, game.win_length, &ctx.accounts.history.moves)?;
+        let mismatch = game.first_board_mismatch(&replay);
+        if let Some((row, col, expected, found)) = mismatch {
+            emit!(BoardMismatch { game: game.key(), row, col, expected, found });
+        }
+        emit!(BoardReconstructed { game: game.key(), matches: mismatch.is_none() });
+        Ok(())
+    } // anti-cheat: a board that doesn't follow from the recorded moves has been tampered with
+
+    pub fn first_divergence(&self, other: &GameHistory) -> Option<(u8, Option<Tile>, Option<Tile>)> {
+        (0..self.moves.len().max(other.moves.len())).find_map(|index| {
+            let (a, b) = (self.moves.get(index).copied(), other.moves.get(index).copied());
+            (a != b).then_some((index as u8 + 1, a, b))
+        })
+    } // (turn, move in self, move in other) at the first turn the recordings differ, turns start at 1
+
+    pub fn match_replay_to_game(ctx: Context<MatchReplayToGame>) -> Result<()> {
+        let (a, b) = (&ctx.accounts.history_a, &ctx.accounts.history_b);
+        match a.first_divergence(b) {
+            None => emit!(HistoriesAgree { game: a.game }),
+            Some((diverge_at_turn, a_move, b_move)) => {
+                let coordinates = |tile: Option<Tile>| tile.map_or((u8::MAX, u8::MAX), |tile| (tile.row, tile.column));
+                let ((a_row, a_col), (b_row, b_col)) = (coordinates(a_move), coordinates(b_move));
+                emit!(HistoriesDisagree { game: a.game, diverge_at_turn, a_row, a_col, b_row, b_col });
+            }
+        }
+        Ok(())
+    } // for disputes where both players recorded the game themselves
+}
+
+impl PlayProof {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 1;
+
+    pub fn proof_of_play(ctx: Context<ProofOfPlay>, commitment: [u8; 32]) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let game = &ctx.accounts.game;
+        let role = game.role_of(&ctx.accounts.player.key());
+        require!(role != u8::MAX, TicTacToeError::NotAPlayer);
+        require!(!game.is_active(), TicTacToeError::GameNotOver);
+
+        let proof = &mut ctx.accounts.proof;
+        require!(proof.game == Pubkey::default(), TicTacToeError::AlreadyProved); // a fresh proof account is still zeroed
+        proof.commitment = commitment;
+        proof.game = game.key();
+        proof.role = role;
+        Ok(())
+    }
+}
+
+impl GameSession {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 4 + (4 * 2) + 1 + (4 * 2) + (1 + 32) + Game::MAXIMUM_SIZE;
+    pub const MAXIMUM_GAMES: u32 = 100; // after this many games the session has to be closed
+
+    pub fn create_session(ctx: Context<CreateSession>, tiebreak_rule: TiebreakRule) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player_one.key(), ctx.remaining_accounts)?;
+        let players = [ctx.accounts.player_one.key(), ctx.accounts.player_two.key()];
+        let session = &mut ctx.accounts.session;
+        session.player_one = players[0];
+        session.player_two = players[1];
+        session.tiebreak_rule = tiebreak_rule;
+        session.game_state.created_via = CreatedVia::Series;
+        ctx.accounts.stats.record_game_created(CreatedVia::Series);
+        session.game_state.start(players)?;
+        session.emit_started(session.key(), &Clock::get()?);
+        Ok(())
+    }
+
+    fn emit_started(&self, session: Pubkey, clock: &Clock) {
+        emit!(GameStarted {
+            game: session,
+            player_one: self.player_one,
+            player_two: self.player_two,
+            timestamp: clock.unix_timestamp,
+            created_via: CreatedVia::Series,
+        });
+    } // session games have no account of their own, the events name the session instead
+
+    pub fn play_session(ctx: Context<PlaySession>, tile: Tile, expected_nonce: u64) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let session = ctx.accounts.session.key();
+        let game = &mut ctx.accounts.session.game_state;
+        require_keys_eq!(game.current_player()?, ctx.accounts.player.key(), TicTacToeError::NotPlayersTurn);
+        game.use_nonce(expected_nonce)?;
+        let turn = game.turn;
+        game.play(&tile)?;
+        emit!(MovePlayed {
+            game: session,
+            player: ctx.accounts.player.key(),
+            row: tile.row,
+            column: tile.column,
+            turn,
+            last_move_tile: game.last_move_tile,
+        });
+        if !game.is_active() {
+            emit!(GameEnded { game: session, state: game.state.clone(), final_turn: game.turn });
+        }
+        Ok(())
+    }
+
+    pub fn advance_session(ctx: Context<AdvanceSession>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let session = &mut ctx.accounts.session;
+        let player = ctx.accounts.player.key();
+        require!(player == session.player_one || player == session.player_two, TicTacToeError::NotAPlayer);
+        require!(session.winner.is_none(), TicTacToeError::SeriesAlreadyDecided);
+        require!(!session.game_state.is_active(), TicTacToeError::GameNotOver);
+        require!(session.games_played < Self::MAXIMUM_GAMES, TicTacToeError::SessionExpired);
+
+        match session.game_state.state {
+            GameState::Won { winner } => {
+                let winner_index = if winner == session.player_one { 0 } else { 1 };
+                session.score[winner_index] += 1;
+            }
+            GameState::Tie => {
+                // player_one starts every game of a session, so they made the odd moves
+                let moves = session.game_state.play_count as u32;
+                session.tied_game_moves[0] += moves.div_ceil(2);
+                session.tied_game_moves[1] += moves / 2;
+            }
+            _ => {}
+        }
+        session.games_played += 1;
+
+        // the last game of a full session stays on the board until the session is closed
+        if session.games_played < Self::MAXIMUM_GAMES {
+            let players = [session.player_one, session.player_two];
+            session.game_state.reset();
+            session.game_state.start(players)?;
+            session.emit_started(session.key(), &Clock::get()?);
+        }
+        Ok(())
+    }
+
+    pub fn finalize_series(ctx: Context<FinalizeSeries>) -> Result<()> {
+        ensure_not_banned(&ctx.accounts.player.key(), ctx.remaining_accounts)?;
+        let ratings = [
+            PlayerProfile::rating_of(&ctx.accounts.profile_one.to_account_info()),
+            PlayerProfile::rating_of(&ctx.accounts.profile_two.to_account_info()),
+        ];
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+        let player = ctx.accounts.player.key();
+        require!(player == session.player_one || player == session.player_two, TicTacToeError::NotAPlayer);
+        require!(session.winner.is_none(), TicTacToeError::SeriesAlreadyDecided);
+        require!(session.games_played > 0, TicTacToeError::GameNotOver);
+        // a game that has started has to be finished and advanced first, only a full session keeps its last board
+        require!(
+            session.game_state.play_count == 0 || session.games_played == Self::MAXIMUM_GAMES,
+            TicTacToeError::SeriesGameInProgress
+        );
+
+        let coin = hashv(&[session_key.as_ref(), &Clock::get()?.slot.to_le_bytes()]).to_bytes()[0] % 2;
+        let Some(index) = session.decide(ratings, coin)? else {
+            return Ok(()); // ExtraGame, the game already set up on the session breaks the tie
+        };
+        let winner = [session.player_one, session.player_two][index];
+        session.winner = Some(winner);
+        let tiebreak = (session.score[0] == session.score[1]).then_some(session.tiebreak_rule);
+        emit!(SeriesDecided { session: session_key, winner, tiebreak });
+        Ok(())
+    }
+
+    pub fn decide(&self, ratings: [i32; 2], coin: u8) -> Result<Option<usize>> {
+        let leader = |ordering: std::cmp::Ordering| match ordering {
+            std::cmp::Ordering::Greater => Ok(0),
+            std::cmp::Ordering::Less => Ok(1),
+            std::cmp::Ordering::Equal => err!(TicTacToeError::TiebreakNotApplicable),
+        };
+        if self.score[0] != self.score[1] {
+            return leader(self.score[0].cmp(&self.score[1])).map(Some);
+        }
+        let index = match self.tiebreak_rule {
+            TiebreakRule::ExtraGame => {
+                require!(self.games_played < Self::MAXIMUM_GAMES, TicTacToeError::TiebreakNotApplicable); // no game left to play
+                return Ok(None);
+            }
+            TiebreakRule::EloDecider => leader(ratings[0].cmp(&ratings[1]))?,
+            TiebreakRule::CoinFlip => coin as usize,
+            TiebreakRule::MostMovesInTiedGames => leader(self.tied_game_moves[0].cmp(&self.tied_game_moves[1]))?,
+        };
+        Ok(Some(index))
+    } // index of the series winner, None while an extra game is still to be played
+}
+
+impl BannedPlayer {
+    pub const MAXIMUM_SIZE: usize = 32 + 32 + 8 + 32;
+
+    pub fn deactivate_player(ctx: Context<DeactivatePlayer>, player: Pubkey, reason_hash: [u8; 32]) -> Result<()> {
+        let banned_player = &mut ctx.accounts.banned_player;
+        banned_player.player = player;
+        banned_player.reason_hash = reason_hash;
+        banned_player.banned_at = Clock::get()?.unix_timestamp;
+        banned_player.banned_by = ctx.accounts.authority.key();
+        Ok(())
+    }
+}
+
+// off-chain only, so tests and fuzz targets can build games without going through Anchor
+#[cfg(not(target_os = "solana"))]
+impl Default for Game {
+    fn default() -> Self {
+        Game {
+            players: [Pubkey::default(); 2],
+            turn: 0,
+            board: [None; Game::MAX_CELLS],
+            board_size: 0,
+            win_length: 0,
+            state: GameState::default(),
+            last_move_tile: None,
+            nonce: 0,
+            room: None,
+            invite_expiry: 0,
+            minimum_elo_requirement: 0,
+            maximum_elo_requirement: None,
+            observer_count: 0,
+            finalized: false,
+            swap_applied: false,
+            ended_at: 0,
+            referee: None,
+            dispute_resolution_timeout: 0,
+            time_bank_p1: 0,
+            time_bank_p2: 0,
+            last_move_at: 0,
+            created_via: CreatedVia::Direct,
+            play_count: 0,
+            version: 0,
+            wager: 0,
+            wager_settled: false,
+            resigned: false,
+            turn_deadline_slots: 0,
+            last_move_slot: 0,
+            moves: [MoveRecord::EMPTY; Game::MAX_CELLS],
+            timed_out: false,
+            results_recorded: [false; 2],
+            draw_offered_by: None,
+            time_bank_enabled: false,
+        }
+    }
+}
+
+#[cfg(not(target_os = "solana"))]
+#[allow(clippy::derivable_impls)] // a derive would put it on-chain too
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::Active
+    }
+}
+
+#[cfg(not(target_os = "solana"))]
+#[allow(clippy::derivable_impls)]
+impl Default for Sign {
+    fn default() -> Self {
+        Sign::X
+    }
+}
+
+/// every state-mutating instruction calls this with the signer and its remaining accounts. Only the authority's
+/// admin instructions and permissionless cranks without a signer (settle, finalize_game, record_result, ...) skip it.
+/// the client has to pass the player's [b"ban", player] PDA, whether it exists or not - if it
+/// deserializes as a BannedPlayer the player is banned. Leaving it out is an error so the check can't be skipped.
+pub fn ensure_not_banned(player: &Pubkey, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    let (ban_address, _) = Pubkey::find_program_address(&[b"ban", player.as_ref()], &crate::ID);
+    let ban_info = remaining_accounts
+        .iter()
+        .find(|account| account.key() == ban_address)
+        .ok_or(TicTacToeError::BanCheckAccountMissing)?;
+    if Account::<BannedPlayer>::try_from(ban_info).is_ok() {
+        return Err(TicTacToeError::PlayerBanned.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sets up a started game with the given X and O tiles, the turn follows from the number of moves
+    fn game_with(x: &[(usize, usize)], o: &[(usize, usize)]) -> Game {
+        let mut game = Game::default();
+        game.start([Pubkey::new_unique(), Pubkey::new_unique()]).unwrap();
+        for (row, column) in x {
+            game.set_cell(*row, *column, Some(Sign::X));
+        }
+        for (row, column) in o {
+            game.set_cell(*row, *column, Some(Sign::O));
+        }
+        game.play_count = (x.len() + o.len()) as u8;
+        game.turn = game.play_count + 1;
+        game
+    }
+
+    #[test]
+    fn timeouts_go_to_the_player_who_is_waiting() {
+        let mut game = game_with(&[(0, 0)], &[]);
+        let x = game.players[0];
+        let settings = ConfigSettings::DEFAULT; // 60 seconds per move
+        assert!(game.timeout_winner(1_000, 1_000, &settings).is_err()); // no deadline set, no time bank
+
+        game.turn_deadline_slots = 10;
+        game.last_move_slot = 100;
+        assert!(game.timeout_winner(110, 0, &settings).is_err());
+        assert_eq!(game.timeout_winner(111, 0, &settings).unwrap(), x); // O is on the move and stalled
+
+        let mut open = Game::default();
+        open.start([x, Pubkey::default()]).unwrap();
+        open.turn_deadline_slots = 10;
+        assert!(open.timeout_winner(1_000, 0, &settings).is_err());
+
+        let mut banked = game_with(&[(0, 0)], &[]);
+        banked.time_bank_enabled = true;
+        banked.time_bank_p2 = 5;
+        assert!(banked.timeout_winner(0, 65, &settings).is_err()); // O is paying from the bank
+        assert_eq!(banked.timeout_winner(0, 66, &settings).unwrap(), banked.players[0]); // and the bank has run out
+    }
+
+    #[test]
+    fn resigning_hands_the_win_to_the_opponent() {
+        let mut game = game_with(&[(0, 0)], &[]);
+        let [x, o] = game.players;
+        assert!(game.forfeit(&Pubkey::new_unique()).is_err());
+        game.forfeit(&x).unwrap(); // not X's turn, resigning doesn't have to wait for it
+        assert!(game.state == GameState::Won { winner: o });
+
+        let mut open = Game::default();
+        open.start([x, Pubkey::default()]).unwrap();
+        assert!(open.forfeit(&x).is_err());
+    }
+
+    #[test]
+    fn board_config_has_to_fit_the_account() {
+        assert!(Game::validate_board_config(3, 3).is_ok());
+        assert!(Game::validate_board_config(5, 4).is_ok());
+        assert!(Game::validate_board_config(4, 5).is_err()); // a line longer than the board can never be completed
+        assert!(Game::validate_board_config(6, 3).is_err());
+        assert!(Game::validate_board_config(3, 2).is_err());
+    }
+
+    #[test]
+    fn bigger_boards_need_win_length_in_a_row() {
+        let players = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let tile = |row, column| Tile { row, column };
+        // X builds a diagonal off the main one, O answers along the top row
+        let moves = [tile(0, 1), tile(0, 0), tile(1, 2), tile(0, 2), tile(2, 3), tile(0, 3)];
+        let mut game = Game::simulate_game(players, 5, 4, &moves).unwrap();
+        assert!(game.is_active()); // three in a row isn't enough anymore
+        assert!(game.find_winning_move(Sign::X) == Some(tile(3, 4)));
+        game.play(&tile(3, 4)).unwrap();
+        assert!(game.check_winner() == Some(Sign::X));
+        assert!(game.state == GameState::Won { winner: players[0] });
+
+        let mut game = Game::simulate_game(players, 4, 4, &[]).unwrap();
+        assert_eq!(game.legal_moves_count(), 16);
+        assert!(game.play(&tile(4, 0)).is_err());
+        assert!(game.play(&tile(3, 3)).is_ok());
+    }
+
+    #[test]
+    fn rematches_let_the_loser_open() {
+        let mut game = game_with(&[(0, 0), (0, 1), (0, 2)], &[(1, 0), (1, 1)]);
+        let [x, o] = game.players;
+        game.state = GameState::Won { winner: x };
+        assert_eq!(game.rematch_players(), [o, x]);
+        game.state = GameState::Won { winner: o };
+        assert_eq!(game.rematch_players(), [x, o]); // X lost, so X opens again
+        game.state = GameState::Tie;
+        assert_eq!(game.rematch_players(), [o, x]);
+    }
+
+    #[test]
+    fn turn_arithmetic_fails_instead_of_wrapping() {
+        assert!(Game::default().current_player().is_err()); // turn 0, start() hasn't run
+        assert!(!Game::default().time_bank_exhausted(i64::MAX, &ConfigSettings::DEFAULT));
+
+        let mut game = game_with(&[], &[]);
+        game.turn = u8::MAX;
+        assert!(game.play(&Tile { row: 0, column: 0 }).is_err());
+    }
+
+    #[test]
+    fn draws_need_the_opponent_to_accept() {
+        let mut game = game_with(&[(0, 0)], &[]);
+        let [x, o] = game.players;
+        assert!(game.accept_draw(&o).is_err()); // nothing offered yet
+        game.offer_draw(&x).unwrap();
+        assert!(game.offer_draw(&o).is_err());
+        assert!(game.accept_draw(&x).is_err());
+        game.decline_draw(&o).unwrap();
+        assert!(game.is_active());
+
+        game.offer_draw(&x).unwrap();
+        game.play(&Tile { row: 1, column: 1 }).unwrap(); // O plays on instead
+        assert!(game.accept_draw(&o).is_err());
+
+        game.offer_draw(&o).unwrap();
+        game.accept_draw(&x).unwrap();
+        assert!(game.state == GameState::Tie);
+        assert_eq!(game.draw_offered_by, None);
+    }
+
+    #[test]
+    fn wager_payouts_follow_the_result() {
+        let settings = ConfigSettings { platform_fee_bps: 100, resign_penalty_bps: 5_000, ..ConfigSettings::DEFAULT };
+        let mut game = game_with(&[(0, 0), (0, 1), (0, 2)], &[(1, 0), (1, 1)]);
+        game.state = GameState::Won { winner: game.players[0] };
+        game.wager = 1_000;
+        assert_eq!(game.wager_payout(&settings), (1_980, 0)); // X won, 1% of the pot is the platform fee
+
+        game.state = GameState::Tie;
+        assert_eq!(game.wager_payout(&settings), (990, 990));
+
+        let mut resigned = game_with(&[(0, 0)], &[]);
+        resigned.wager = 1_000;
+        let o = resigned.players[1];
+        resigned.forfeit(&o).unwrap();
+        assert_eq!(resigned.wager_payout(&settings), (990, 990)); // the resigner keeps half of what's left
+    }
+
+    #[test]
+    fn abandoned_vaults_are_split_between_the_players() {
+        let mut game = game_with(&[], &[]);
+        assert_eq!(game.abandoned_vault_split(2_000), (1_000, 1_000));
+        assert_eq!(game.abandoned_vault_split(2_001), (1_001, 1_000));
+        game.players[1] = Pubkey::default(); // an open game only holds player one's wager
+        assert_eq!(game.abandoned_vault_split(1_000), (1_000, 0));
+    }
+
+    #[test]
+    fn move_history_lists_the_moves_in_order() {
+        let mut game = game_with(&[], &[]);
+        assert!(game.get_move_history().is_empty());
+        for (row, column) in [(1, 1), (0, 2)] {
+            game.play(&Tile { row, column }).unwrap();
+        }
+        let history: Vec<(u8, u8, Sign)> = game.get_move_history().iter().map(|m| (m.row, m.column, m.sign)).collect();
+        assert!(history == vec![(1, 1, Sign::X), (0, 2, Sign::O)]);
+        game.reset();
+        assert!(game.get_move_history().is_empty());
+    }
+
+    #[test]
+    fn play_count_follows_turn() {
+        let mut game = game_with(&[], &[]);
+        for (row, column) in [(0, 0), (1, 1), (2, 2)] {
+            game.play(&Tile { row, column }).unwrap();
+            assert_eq!(game.play_count, game.turn - 1);
+            assert_eq!(game.play_count, game.cell_count() - game.legal_moves_count());
+        }
+        game.finalized = true;
+        game.results_recorded = [true, true];
+        game.reset();
+        assert_eq!(game.play_count, 0);
+        assert!(!game.finalized && game.results_recorded == [false, false]); // the replay counts on its own
+    }
+
+    #[test]
+    fn legal_moves_count_tracks_a_whole_game() {
+        let moves = [(1, 1), (0, 0), (0, 2), (2, 0), (1, 0), (1, 2), (0, 1), (2, 1), (2, 2)];
+        let mut game = game_with(&[], &[]);
+        assert_eq!(game.legal_moves_count(), 9);
+        for (played, (row, column)) in moves.into_iter().enumerate() {
+            assert_eq!(game.legal_moves_count() as usize, game.valid_moves().len());
+            game.play(&Tile { row, column }).unwrap();
+            assert_eq!(game.legal_moves_count() as usize, 8 - played);
+        }
+        assert_eq!(game.legal_moves_count(), 0);
+        assert!(game.state == GameState::Tie);
+    }
+
+    #[test]
+    fn default_game_matches_a_fresh_account() {
+        let fresh = Game::deserialize(&mut &[0u8; Game::MAXIMUM_SIZE][..]).unwrap();
+        assert_eq!(fresh.try_to_vec().unwrap(), Game::default().try_to_vec().unwrap());
+        assert!(Game::default().is_active());
+    }
+
+    #[test]
+    fn maximum_size_fits_the_largest_game() {
+        // every Option set and the state carrying a winner, the largest a Game can serialize to
+        let mut game = game_with(&[], &[]);
+        game.board = [Some(Sign::X); Game::MAX_CELLS]; // a full 5x5 board
+        game.state = GameState::Won { winner: Pubkey::new_unique() };
+        game.last_move_tile = Some(Tile { row: 2, column: 2 });
+        game.room = Some(Pubkey::new_unique());
+        game.maximum_elo_requirement = Some(2000);
+        game.referee = Some(Pubkey::new_unique());
+        game.draw_offered_by = Some(Pubkey::new_unique());
+        assert_eq!(game.try_to_vec().unwrap().len(), Game::MAXIMUM_SIZE);
+    }
+
+    #[test]
+    fn detects_zugzwang_against_a_fork() {
+        // X threatens both the top row and the left column, O can only block one of them
+        let game = game_with(&[(0, 0), (0, 1), (1, 0)], &[(1, 1), (2, 2)]);
+        assert!(game.find_winning_move(Sign::X).is_some());
+        assert!(game.is_zugzwang());
+    }
+
+    #[test]
+    fn open_positions_are_not_zugzwang() {
+        assert!(!game_with(&[], &[]).is_zugzwang());
+        // O can still block the only threat
+        assert!(!game_with(&[(0, 0), (0, 1)], &[(1, 1)]).is_zugzwang());
+    }
+
+    #[test]
+    fn expected_outcome_follows_perfect_play() {
+        // the empty board and every opening are known draws
+        assert_eq!(game_with(&[], &[]).expected_outcome(), Some(0));
+        assert_eq!(game_with(&[(0, 1)], &[]).expected_outcome(), Some(0));
+        // too open to search on chain, and nothing to search before the game starts
+        assert_eq!(game_with(&[(0, 0), (1, 1)], &[(0, 1)]).expected_outcome(), None);
+        assert_eq!(Game::default().expected_outcome(), None);
+        // X completes the top row
+        assert_eq!(game_with(&[(0, 0), (0, 1)], &[(1, 1), (2, 2)]).expected_outcome(), Some(1));
+        // neither side can force a win from here
+        assert_eq!(game_with(&[(1, 1), (0, 1)], &[(0, 0), (2, 1)]).expected_outcome(), Some(0));
+        // O to move against X's fork is lost
+        assert_eq!(game_with(&[(0, 0), (0, 1), (1, 0)], &[(1, 1), (2, 2)]).expected_outcome(), Some(-1));
+    }
+
+    #[test]
+    fn endgame_starts_with_three_moves_left() {
+        let opening = game_with(&[(1, 1), (0, 0)], &[(0, 2), (2, 0)]);
+        assert_eq!(opening.legal_moves_count(), 5);
+        assert!(!opening.is_endgame());
+
+        let endgame = game_with(&[(1, 1), (0, 1), (2, 0)], &[(0, 0), (2, 1), (0, 2)]);
+        assert_eq!(endgame.legal_moves_count(), 3);
+        assert!(endgame.is_endgame());
+    }
+
+    #[test]
+    fn time_bank_saves_up_and_pays_overtime() {
+        let settings = ConfigSettings::DEFAULT; // 60 seconds per move, up to 10 saved per move
+        let mut game = game_with(&[], &[]);
+        game.time_bank_p1 = 30;
+        game.time_bank_p2 = 30;
+
+        game.charge_move_time(5, &settings).unwrap(); // 55 seconds spare, capped at 10
+        assert_eq!((game.time_bank_p1, game.time_bank_p2), (40, 30));
+        game.play(&Tile { row: 1, column: 1 }).unwrap();
+
+        game.charge_move_time(5 + 80, &settings).unwrap(); // 20 seconds over the limit
+        assert_eq!((game.time_bank_p1, game.time_bank_p2), (40, 10));
+        game.play(&Tile { row: 0, column: 0 }).unwrap();
+        game.play(&Tile { row: 2, column: 2 }).unwrap();
+
+        assert!(!game.time_bank_exhausted(85 + 60 + 10, &settings)); // O still has 10 seconds in the bank
+        assert!(game.time_bank_exhausted(85 + 60 + 11, &settings));
+        game.charge_move_time(85 + 60 + 25, &settings).unwrap(); // more overtime than the bank holds
+        assert_eq!(game.time_bank_p2, 0);
+
+        game.play(&Tile { row: 0, column: 2 }).unwrap();
+        game.time_bank_p1 = u32::MAX;
+        game.charge_move_time(85 + 60 + 25, &settings).unwrap(); // X's quick move can't overflow a full bank
+        assert_eq!(game.time_bank_p1, u32::MAX);
+    }
+
+    #[test]
+    fn referee_authority_is_checked_and_lapses() {
+        let mut game = Game::default();
+        let referee = Pubkey::new_unique();
+        assert!(game.ensure_referee(&referee, 0).is_err()); // no referee set
+
+        game.referee = Some(referee);
+        game.dispute_resolution_timeout = 100;
+        assert!(game.ensure_referee(&referee, 100).is_ok());
+        assert!(game.ensure_referee(&Pubkey::new_unique(), 100).is_err());
+        assert!(game.ensure_referee(&referee, 101).is_err());
+    }
+
+    #[test]
+    fn score_prefers_the_center_and_open_pairs() {
+        let center = game_with(&[(1, 1)], &[]);
+        let corner = game_with(&[(0, 0)], &[]);
+        assert!(center.score(Sign::X) > corner.score(Sign::X));
+
+        let pair = game_with(&[(0, 0), (0, 1)], &[(2, 2)]);
+        let isolated = game_with(&[(0, 0), (2, 1)], &[(2, 2)]);
+        assert!(pair.score(Sign::X) > isolated.score(Sign::X));
+        assert_eq!(pair.score(Sign::O), -pair.score(Sign::X));
+
+        let won = game_with(&[(0, 0), (0, 1), (0, 2)], &[(1, 1), (2, 2)]);
+        assert_eq!(won.score(Sign::X), 100);
+        assert_eq!(won.score(Sign::O), -100);
+
+        // pairs all over a 5x5 board with three in a row to win still score below a won game
+        let mut crowded = Game::simulate_game([Pubkey::new_unique(), Pubkey::new_unique()], 5, 3, &[]).unwrap();
+        for row in [0, 2, 4] {
+            for column in [0, 1, 3, 4] {
+                crowded.set_cell(row, column, Some(Sign::X));
+            }
+        }
+        assert!(crowded.is_active());
+        assert_eq!(crowded.score(Sign::X), 99);
+        assert_eq!(crowded.score(Sign::O), -99);
+    }
+
+    #[test]
+    fn friends_lists_add_remove_and_fill_up() {
+        let mut list = FriendsList { owner: Pubkey::new_unique(), friends: [Pubkey::default(); 50] };
+        let friend = Pubkey::new_unique();
+        list.add(friend).unwrap();
+        assert!(list.contains(&friend));
+        assert!(list.add(friend).is_err());
+        assert!(list.add(list.owner).is_err());
+        list.remove(&friend).unwrap();
+        assert!(!list.contains(&friend));
+        assert!(!list.contains(&Pubkey::default())); // empty slots are nobody's friend
+
+        for _ in 0..FriendsList::MAXIMUM_FRIENDS {
+            list.add(Pubkey::new_unique()).unwrap();
+        }
+        assert!(list.add(Pubkey::new_unique()).is_err()); // the 51st friend
+
+        let mut other = FriendsList { owner: Pubkey::new_unique(), friends: [Pubkey::default(); 50] };
+        other.add(list.friends[7]).unwrap();
+        other.add(Pubkey::new_unique()).unwrap();
+        assert_eq!(list.mutual(&other), vec![list.friends[7]]);
+    }
+
+    #[test]
+    fn rooms_admit_ratings_between_floor_and_cap() {
+        let room = GameRoom {
+            name: [0; 32],
+            creator: Pubkey::new_unique(),
+            config: GameRoomConfig { board_size: 3, win_length: 3, custom_rules: 0 },
+            member_count: 0,
+            active_games: 0,
+            elo_cap: 1500,
+            min_elo: 1000,
+            min_games_before_ranking: 0,
+            rating_decay_rate: 0,
+            last_decay_slot: 0,
+            featured_game: None,
+        };
+        assert!(room.check_elo(1000).is_ok());
+        assert!(room.check_elo(1500).is_ok());
+        assert!(room.check_elo(999).is_err());
+        assert!(room.check_elo(1501).is_err());
+    }
+
+    #[test]
+    fn only_inactive_room_members_decay() {
+        let mut member = RoomMember {
+            room: Pubkey::new_unique(),
+            player: Pubkey::new_unique(),
+            joined_at: 0,
+            calibration_remaining: 0,
+            unranked: false,
+            elo_rating: 1500,
+            last_active_slot: 1_000,
+        };
+        assert!(!member.decay(10, 1_000 + SLOTS_PER_WEEK)); // played within the week
+        assert!(!member.decay(0, 1_000 + SLOTS_PER_WEEK + 1)); // the room doesn't decay
+        assert!(member.decay(10, 1_000 + SLOTS_PER_WEEK + 1));
+        assert_eq!(member.elo_rating, 1350);
+    }
+
+    #[test]
+    fn room_members_rank_after_calibration() {
+        let mut member = RoomMember {
+            room: Pubkey::new_unique(),
+            player: Pubkey::new_unique(),
+            joined_at: 0,
+            calibration_remaining: 2,
+            unranked: true,
+            elo_rating: STARTING_ELO,
+            last_active_slot: 0,
+        };
+        assert!(!member.record_calibration_game());
+        assert!(member.unranked);
+        assert!(member.record_calibration_game());
+        assert!(!member.unranked);
+        assert!(!member.record_calibration_game()); // unlocked only once
+    }
+
+    #[test]
+    fn daily_leaderboard_ranks_by_time_then_attempts() {
+        let mut leaderboard = DailyLeaderboard { date: 20_000, entries: Vec::new() };
+        let now = 20_000 * 86_400;
+        let entry = |solved_at, attempts_used| DailyEntry { solver: Pubkey::new_unique(), solved_at, attempts_used };
+        let (slow, fast, fast_fewer_attempts) = (entry(now + 50, 1), entry(now + 10, 3), entry(now + 10, 1));
+        for solver in [&slow, &fast, &fast_fewer_attempts] {
+            leaderboard.insert(solver.clone(), now + 60).unwrap();
+        }
+        leaderboard.insert(DailyEntry { solved_at: now, ..slow.clone() }, now + 60).unwrap(); // already on the board
+        assert_eq!(leaderboard.entries, vec![fast_fewer_attempts, fast, slow]);
+
+        for offset in 0..30 {
+            leaderboard.insert(entry(now + offset, 1), now + 60).unwrap();
+        }
+        assert_eq!(leaderboard.entries.len(), DailyLeaderboard::MAXIMUM_ENTRIES);
+    }
+
+    #[test]
+    fn daily_leaderboard_freezes_a_day_after_the_challenge() {
+        let mut leaderboard = DailyLeaderboard { date: 20_000, entries: Vec::new() };
+        let entry = DailyEntry { solver: Pubkey::new_unique(), solved_at: 0, attempts_used: 1 };
+        assert!(leaderboard.insert(entry.clone(), 20_002 * 86_400 - 1).is_ok());
+        assert!(leaderboard.insert(entry, 20_002 * 86_400).is_err());
+    }
+
+    #[test]
+    fn daily_puzzles_are_won_in_one_move() {
+        for date in 20_000..20_010 {
+            let puzzle = DailyLeaderboard::puzzle(date).unwrap();
+            assert!(puzzle.is_active());
+            assert!(puzzle.board == DailyLeaderboard::puzzle(date).unwrap().board); // the same for everyone
+            let sign = Sign::from_usize(puzzle.current_player_index().unwrap()).unwrap();
+            assert!(puzzle.find_winning_move(sign).is_some());
+        }
+    }
+
+    #[test]
+    fn rate_limit_resets_every_slot() {
+        let settings = ConfigSettings { max_calls_per_slot: 2, ..ConfigSettings::DEFAULT };
+        let mut limit = InstructionRateLimit { instruction_id: InstructionRateLimit::ANALYZE_THREATS, calls_this_slot: 0, slot: 0 };
+        assert!(limit.record_call(10, &settings).is_ok());
+        assert!(limit.record_call(10, &settings).is_ok());
+        assert!(limit.record_call(10, &settings).is_err());
+        assert_eq!(limit.calls_this_slot, 2); // a rejected call isn't counted
+
+        assert!(limit.record_call(11, &settings).is_ok());
+        assert_eq!((limit.slot, limit.calls_this_slot), (11, 1));
+    }
+
+    #[test]
+    fn profiles_count_wins_losses_and_draws() {
+        let mut game = game_with(&[(0, 0), (0, 1), (0, 2)], &[(1, 0), (1, 1)]);
+        let [x, o] = game.players;
+        game.state = GameState::Won { winner: x };
+        let profile = |owner| PlayerProfile {
+            owner,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            games_played: 0,
+            abandons: AbandonRecord::default(),
+            elo_rating: STARTING_ELO,
+            seasonal_rank: SeasonalRank::Unranked,
+            tournament_wins: 0,
+            tournament_runner_up: 0,
+        };
+        let (mut winner, mut loser) = (profile(x), profile(o));
+        winner.record(&game, STARTING_ELO);
+        loser.record(&game, STARTING_ELO);
+        assert_eq!((winner.elo_rating, loser.elo_rating), (STARTING_ELO + 16, STARTING_ELO - 16));
+        game.state = GameState::Tie;
+        winner.record(&game, loser.elo_rating);
+        assert_eq!((winner.wins, winner.losses, winner.draws, winner.games_played), (1, 0, 1, 2));
+        assert_eq!((loser.wins, loser.losses, loser.draws, loser.games_played), (0, 1, 0, 1));
+        assert_eq!(winner.elo_rating, STARTING_ELO + 15); // a draw against a weaker player costs a little
+
+        game.state = GameState::Cancelled { cancelled_by: x };
+        winner.record(&game, STARTING_ELO);
+        assert_eq!(winner.elo_rating, STARTING_ELO + 15);
+
+        game.state = GameState::Won { winner: x };
+        game.timed_out = true;
+        for _ in 0..2 {
+            assert_eq!(loser.record(&game, STARTING_ELO), None);
+        }
+        assert_eq!(loser.record(&game, STARTING_ELO), Some(3)); // only the player who ran out of time abandoned the game
+        assert_eq!(winner.record(&game, STARTING_ELO), None);
+        assert_eq!(winner.abandons.games_abandoned, 0);
+    }
+
+    #[test]
+    fn elo_requirements_bound_both_sides() {
+        let mut game = Game { minimum_elo_requirement: 1300, ..Game::default() };
+        assert!(game.check_elo_requirement(1300).is_ok());
+        assert!(game.check_elo_requirement(1299).is_err());
+        game.maximum_elo_requirement = Some(1400);
+        assert!(game.check_elo_requirement(1400).is_ok());
+        assert!(game.check_elo_requirement(1401).is_err());
+    }
+
+    #[test]
+    fn abandons_lock_out_until_recovered() {
+        let settings = ConfigSettings::DEFAULT;
+        let mut record = AbandonRecord::default();
+        let warnings: Vec<u32> = (0..settings.max_allowed_abandons).filter_map(|_| record.record_abandon()).collect();
+        assert_eq!(warnings, vec![3, 5]);
+        assert!(record.ensure_allowed(&settings).is_ok());
+
+        assert_eq!(record.record_abandon(), None);
+        assert!(record.ensure_allowed(&settings).is_err());
+        for _ in 0..settings.abandon_recovery_games {
+            record.record_completion();
+        }
+        assert!(record.ensure_allowed(&settings).is_ok());
+
+        record.record_abandon(); // a new abandon starts the recovery over
+        assert!(record.ensure_allowed(&settings).is_err());
+    }
+
+    #[test]
+    fn random_games_follow_their_seed() {
+        let (moves, game) = Game::random_game(&[7; 32]).unwrap();
+        let (again, replay) = Game::random_game(&[7; 32]).unwrap();
+        assert_eq!(moves, again);
+        assert!(game.first_board_mismatch(&replay).is_none());
+        assert!(!game.is_active());
+
+        let games: std::collections::HashSet<Vec<u8>> =
+            (0..32u8).map(|seed| Game::random_game(&[seed; 32]).unwrap().0).collect();
+        assert!(games.len() > 16); // different seeds mostly give different games
+    }
+
+    #[test]
+    fn histories_report_where_they_diverge() {
+        let game = Pubkey::new_unique();
+        let moves = vec![Tile { row: 1, column: 1 }, Tile { row: 0, column: 0 }, Tile { row: 2, column: 2 }];
+        let a = GameHistory { game, recorder: Pubkey::new_unique(), moves: moves.clone() };
+        let mut b = GameHistory { game, recorder: Pubkey::new_unique(), moves };
+        assert_eq!(a.first_divergence(&b), None);
+
+        b.moves[1] = Tile { row: 0, column: 2 };
+        assert_eq!(
+            a.first_divergence(&b),
+            Some((2, Some(Tile { row: 0, column: 0 }), Some(Tile { row: 0, column: 2 })))
+        );
+
+        b.moves = a.moves[..2].to_vec(); // b stopped recording early
+        assert_eq!(a.first_divergence(&b), Some((3, Some(Tile { row: 2, column: 2 }), None)));
+    }
+
+    #[test]
+    fn replayed_history_matches_the_board() {
+        let game = game_with(&[(0, 0), (2, 2)], &[(1, 1)]);
+        let moves = [Tile { row: 0, column: 0 }, Tile { row: 1, column: 1 }, Tile { row: 2, column: 2 }];
+        let replay = Game::simulate_game(game.players, 3, 3, &moves).unwrap();
+        assert_eq!(game.first_board_mismatch(&replay), None);
+    }
+
+    #[test]
+    fn tampered_board_is_reported() {
+        let moves = [Tile { row: 0, column: 0 }, Tile { row: 1, column: 1 }];
+        let mut game = Game::simulate_game([Pubkey::new_unique(), Pubkey::new_unique()], 3, 3, &moves).unwrap();
+        let replay = game.clone();
+        game.set_cell(1, 1, Some(Sign::X)); // O's move rewritten into an X
+        assert_eq!(game.first_board_mismatch(&replay), Some((1, 1, 2, 1)));
+    }
+
+    #[test]
+    fn recompute_state_catches_a_corrupted_state() {
+        let mut game = Game::default();
+        let players = [Pubkey::new_unique(), Pubkey::new_unique()];
+        game.start(players).unwrap();
+        for tile in [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)] {
+            game.play(&Tile { row: tile.0, column: tile.1 }).unwrap();
+        }
+        assert!(game.recompute_state() == game.state);
+
+        game.state = GameState::Tie; // what a buggy or tampered account could hold
+        let computed = game.recompute_state();
+        assert!(computed != game.state);
+        assert!(computed == GameState::Won { winner: players[0] });
+
+        // X's row with no O on the board can't come from alternating moves, the shortcut mustn't hide it
+        let mut tampered = Game::default();
+        tampered.start(players).unwrap();
+        for column in 0..3 {
+            tampered.set_cell(0, column, Some(Sign::X));
+        }
+        assert!(tampered.recompute_state() == GameState::Won { winner: players[0] });
+    }
+
+    #[test]
+    fn seasonal_rank_changes_at_each_threshold() {
+        let thresholds = ConfigSettings::DEFAULT.rank_thresholds;
+        let ranks = [
+            SeasonalRank::Bronze,
+            SeasonalRank::Silver,
+            SeasonalRank::Gold,
+            SeasonalRank::Platinum,
+            SeasonalRank::Diamond,
+        ];
+        let mut below = SeasonalRank::Unranked;
+        for (threshold, rank) in thresholds.iter().zip(ranks) {
+            assert_eq!(SeasonalRank::from_elo(threshold - 1, &thresholds), below);
+            assert_eq!(SeasonalRank::from_elo(*threshold, &thresholds), rank);
+            below = rank;
+        }
+    }
+
+    #[test]
+    fn seasonal_rank_drops_on_demotion() {
+        let thresholds = ConfigSettings::DEFAULT.rank_thresholds;
+        let old = SeasonalRank::from_elo(1300, &thresholds);
+        let new = SeasonalRank::from_elo(1299, &thresholds);
+        assert_eq!((old, new), (SeasonalRank::Silver, SeasonalRank::Bronze));
+        assert!(SeasonalRank::ensure_ranked_eligible(1300, &thresholds).is_ok());
+        assert!(SeasonalRank::ensure_ranked_eligible(1299, &thresholds).is_err());
+    }
+
+    #[test]
+    fn profile_rank_follows_the_rating() {
+        let thresholds = ConfigSettings::DEFAULT.rank_thresholds;
+        let mut profile = PlayerProfile {
+            owner: Pubkey::new_unique(),
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            games_played: 0,
+            abandons: AbandonRecord::default(),
+            elo_rating: STARTING_ELO,
+            seasonal_rank: SeasonalRank::Unranked,
+            tournament_wins: 0,
+            tournament_runner_up: 0,
+        };
+        assert_eq!(profile.update_rank(&thresholds), Some((SeasonalRank::Unranked, SeasonalRank::Bronze)));
+        profile.elo_rating = 1299;
+        assert_eq!(profile.update_rank(&thresholds), None); // still Bronze, nothing to report
+        profile.elo_rating = 1300;
+        assert_eq!(profile.update_rank(&thresholds), Some((SeasonalRank::Bronze, SeasonalRank::Silver)));
+        profile.elo_rating = 1299;
+        assert_eq!(profile.update_rank(&thresholds), Some((SeasonalRank::Silver, SeasonalRank::Bronze)));
+        assert_eq!(profile.seasonal_rank, SeasonalRank::Bronze);
+    }
+
+    #[test]
+    fn tournament_finals_are_counted_on_profiles() {
+        let profile = |owner| PlayerProfile {
+            owner,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            games_played: 0,
+            abandons: AbandonRecord::default(),
+            elo_rating: STARTING_ELO,
+            seasonal_rank: SeasonalRank::Unranked,
+            tournament_wins: 0,
+            tournament_runner_up: 0,
+        };
+        let (mut champion, mut finalist) = (profile(Pubkey::new_unique()), profile(Pubkey::new_unique()));
+        champion.record_tournament_final(champion.owner);
+        finalist.record_tournament_final(champion.owner);
+        assert_eq!((champion.tournament_wins, champion.tournament_runner_up), (1, 0));
+        assert_eq!((finalist.tournament_wins, finalist.tournament_runner_up), (0, 1));
+    }
+
+    #[test]
+    fn lobby_swap_removes_and_fills_up() {
+        let mut lobby = GameLobby { pending_games: [Pubkey::default(); GameLobby::CAPACITY], count: 0 };
+        let games: Vec<Pubkey> = (0..GameLobby::CAPACITY).map(|_| Pubkey::new_unique()).collect();
+        for game in &games {
+            lobby.add_game(*game).unwrap();
+        }
+        assert!(lobby.add_game(Pubkey::new_unique()).is_err());
+
+        assert!(lobby.remove_game(&games[0]));
+        assert!(!lobby.remove_game(&games[0]));
+        assert_eq!(lobby.pending().len(), GameLobby::CAPACITY - 1);
+        assert_eq!(lobby.pending()[0], games[GameLobby::CAPACITY - 1]); // the last entry took the free slot
+        assert!(lobby.add_game(Pubkey::new_unique()).is_ok());
+    }
+
+    #[test]
+    fn tournament_brackets_pair_up_neighbouring_winners() {
+        let mut tournament = Tournament {
+            organizer: Pubkey::new_unique(),
+            participants: [Pubkey::default(); 8],
+            participant_count: 0,
+            capacity: 8,
+            games: [Pubkey::default(); 7],
+            winners: [Pubkey::default(); 7],
+            round: 0,
+            winner: None,
+            prize_shares: Vec::new(),
+            ranked: false,
+        };
+        let players: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        for player in &players {
+            tournament.register(*player).unwrap();
+        }
+        assert!(tournament.register(Pubkey::new_unique()).is_err());
+        assert_eq!(
+            [tournament.round_slots(1), tournament.round_slots(2), tournament.round_slots(3)],
+            [0..4, 4..6, 6..7]
+        );
+
+        tournament.round = 1;
+        tournament.winners[..3].copy_from_slice(&[players[0], players[3], players[4]]);
+        assert!(tournament.next_round_pairs().is_err()); // the fourth game is still going
+        tournament.winners[3] = players[7];
+        assert_eq!(tournament.next_round_pairs().unwrap(), vec![[players[0], players[3]], [players[4], players[7]]]);
+
+        tournament.round = 3;
+        tournament.winners[6] = players[3];
+        assert!(tournament.next_round_pairs().unwrap().is_empty()); // the final has been played
+
+        tournament.winners[4..6].copy_from_slice(&[players[3], players[7]]);
+        assert_eq!(tournament.slot_players(5), [players[4], players[7]]);
+        assert_eq!(tournament.slot_players(6), [players[3], players[7]]);
+        assert!(tournament.placements().is_empty()); // no winner recorded yet
+        tournament.winner = Some(players[3]);
+        let order = [3, 7, 0, 4, 1, 2, 5, 6]; // champion, finalist, semi-finalists, then round one
+        assert_eq!(tournament.placements(), order.map(|index| players[index]).to_vec());
+    }
+
+    #[test]
+    fn bracket_slots_have_to_match_their_games() {
+        let players: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut participants = [Pubkey::default(); 8];
+        participants[..4].copy_from_slice(&players);
+        let mut tournament = Tournament {
+            organizer: players[0],
+            participants,
+            participant_count: 4,
+            capacity: 4,
+            games: [Pubkey::default(); 7],
+            winners: [Pubkey::default(); 7],
+            round: 1,
+            winner: None,
+            prize_shares: Vec::new(),
+            ranked: false,
+        };
+        let mut game = game_with(&[], &[]);
+        game.players = [players[0], players[1]];
+        game.created_via = CreatedVia::Tournament;
+        assert!(tournament.slot_matches(0, &game)); // still being played
+        assert!(!tournament.slot_matches(1, &game)); // somebody else's match
+
+        game.state = GameState::Won { winner: players[1] };
+        tournament.winners[0] = players[1];
+        assert!(tournament.slot_matches(0, &game));
+        game.players.swap(0, 1); // replayed after a tie
+        assert!(tournament.slot_matches(0, &game));
+
+        tournament.winners[0] = players[0]; // recorded for the loser
+        assert!(!tournament.slot_matches(0, &game));
+        tournament.winners[0] = players[1];
+        game.created_via = CreatedVia::Direct;
+        assert!(!tournament.slot_matches(0, &game));
+    }
+
+    #[test]
+    fn series_ties_are_broken_by_the_tiebreak_rule() {
+        let mut session = GameSession {
+            player_one: Pubkey::new_unique(),
+            player_two: Pubkey::new_unique(),
+            games_played: 3,
+            score: [2, 1],
+            tiebreak_rule: TiebreakRule::EloDecider,
+            tied_game_moves: [0; 2],
+            winner: None,
+            game_state: Game::default(),
+        };
+        assert_eq!(session.decide([1200, 1400], 1).unwrap(), Some(0)); // more wins beat any tiebreak
+
+        session.score = [1, 1];
+        assert_eq!(session.decide([1200, 1400], 0).unwrap(), Some(1));
+        assert!(session.decide([1300, 1300], 0).is_err()); // equal ratings can't break the tie
+
+        session.tiebreak_rule = TiebreakRule::CoinFlip;
+        assert_eq!(session.decide([1300, 1300], 1).unwrap(), Some(1));
+
+        session.tiebreak_rule = TiebreakRule::MostMovesInTiedGames;
+        session.tied_game_moves = [5, 4];
+        assert_eq!(session.decide([1300, 1300], 1).unwrap(), Some(0));
+        session.tied_game_moves = [9, 9];
+        assert!(session.decide([1300, 1300], 1).is_err());
+
+        session.tiebreak_rule = TiebreakRule::ExtraGame;
+        assert_eq!(session.decide([1300, 1300], 1).unwrap(), None); // decided by the next game
+        session.games_played = GameSession::MAXIMUM_GAMES;
+        assert!(session.decide([1300, 1300], 1).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::token::spl_token;
+
+use crate::TicTacToeError;
+
+/// checks that `token_account` is an SPL token account holding at least one unfrozen `mint` token owned by `owner`.
+/// Returns Ok(false) instead of an error when the account doesn't qualify, so NFT-gated instructions can decide
+/// for themselves whether that's fatal. Only data that isn't a token account at all is an error.
+pub fn verify_nft_ownership(owner: Pubkey, mint: Pubkey, token_account: &AccountInfo) -> Result<bool> {
+    if *token_account.owner != spl_token::ID {
+        return Ok(false); // anyone can write token-shaped data into an account of their own program
+    }
+    let token_state = spl_token::state::Account::unpack(&token_account.try_borrow_data()?)?;
+    Ok(token_state.owner == owner
+        && token_state.mint == mint
+        && token_state.amount >= 1
+        && !token_state.is_frozen())
+}
+
+/// splits the pot of a wager game that ended by resignation into (winner, resigner, platform) lamports.
+/// `wager` is what each player put in. The platform fee comes off the pot first and the winner gets
+/// `penalty_bps` of what's left, the resigner gets the rest back - so the three parts always add up to `2 * wager`.
+pub fn compute_resign_payout(wager: u64, penalty_bps: u16, fee_bps: u16) -> (u64, u64, u64) {
+    let pot = wager as u128 * 2;
+    let platform = pot * fee_bps.min(10_000) as u128 / 10_000;
+    let winner = (pot - platform) * penalty_bps.min(10_000) as u128 / 10_000;
+    let resigner = pot - platform - winner;
+    (winner as u64, resigner as u64, platform as u64)
+}
+
+/// splits a prize pool by placement. `prize_shares` holds basis points per placement (first place first) and
+/// has to add up to 10000. Rounding dust goes to first place so the amounts always add up to `total`.
+pub fn compute_prize_distribution(total: u64, prize_shares: &[u16]) -> Result<Vec<u64>> {
+    let share_sum: u32 = prize_shares.iter().map(|share| *share as u32).sum();
+    require_eq!(share_sum, 10_000, TicTacToeError::InvalidPrizeShares);
+
+    let mut amounts: Vec<u64> = prize_shares
+        .iter()
+        .map(|share| (total as u128 * *share as u128 / 10_000) as u64)
+        .collect();
+    let distributed: u64 = amounts.iter().sum();
+    amounts[0] += total - distributed;
+    Ok(amounts)
+}
+
+pub const ELO_K_FACTOR: i64 = 32;
+
+// expected score in basis points of the higher rated player, for rating differences of 0, 25, 50, ... 400
+const EXPECTED_SCORE_BPS: [i64; 17] =
+    [5000, 5359, 5715, 6063, 6401, 6725, 7034, 7325, 7597, 7850, 8083, 8296, 8490, 8666, 8823, 8965, 9091];
+
+/// rating change of a player rated `rating` who scored `score_bps` (10000 win, 5000 draw, 0 loss) against
+/// `opponent_rating`. The expected score is interpolated from the logistic curve without floats, and differences
+/// beyond 400 count as 400 so a favourite always has something to win. Both players' changes add up to zero.
+pub fn compute_elo_change(rating: i32, opponent_rating: i32, score_bps: u16) -> i32 {
+    let difference = (rating as i64 - opponent_rating as i64).clamp(-400, 400);
+    let (step, rest) = ((difference.abs() / 25) as usize, difference.abs() % 25);
+    let mut expected = EXPECTED_SCORE_BPS[step];
+    if rest > 0 {
+        expected += (EXPECTED_SCORE_BPS[step + 1] - expected) * rest / 25;
+    }
+    if difference < 0 {
+        expected = 10_000 - expected;
+    }
+    let change = ELO_K_FACTOR * (score_bps.min(10_000) as i64 - expected);
+    ((change + change.signum() * 5_000) / 10_000) as i32 // rounded half away from zero, the same for both sides
+}
+
+/// feature bits for `estimate_compute_units`
+pub const HINT_FLAG: u8 = 1 << 0;
+pub const HISTORY_FLAG: u8 = 1 << 1;
+pub const ELO_UPDATE_FLAG: u8 = 1 << 2;
+
+/// a generous compute unit budget for a `play` on a `board_size` board with the given feature bits, meant for
+/// `ComputeBudgetProgram.setComputeUnitLimit` on the client. Unknown bits are ignored.
+pub fn estimate_compute_units(board_size: u8, feature_flags: u8) -> u64 {
+    let mut units = 5_000 + board_size as u64 * 500;
+    if feature_flags & HINT_FLAG != 0 {
+        units += 20_000;
+    }
+    if feature_flags & HISTORY_FLAG != 0 {
+        units += 50_000;
+    }
+    if feature_flags & ELO_UPDATE_FLAG != 0 {
+        units += 100_000;
+    }
+    units
+}
+
+/// compute units one negamax node is budgeted at, a generous figure for the largest board
+pub const SEARCH_NODE_COMPUTE_UNITS: u64 = 2_000;
+/// most free cells `Game::expected_outcome` searches, more than that wouldn't fit a transaction
+pub const MAX_SEARCH_FREE_CELLS: u8 = 5;
+
+/// an upper bound on the compute units of a full negamax over `free_cells` empty cells, counting every order the
+/// cells can be filled in as if no line were ever completed
+pub fn estimate_search_compute_units(free_cells: u8) -> u64 {
+    let (mut nodes, mut orders) = (1u64, 1u64);
+    for left in (1..=free_cells as u64).rev() {
+        orders = orders.saturating_mul(left);
+        nodes = nodes.saturating_add(orders);
+    }
+    nodes.saturating_mul(SEARCH_NODE_COMPUTE_UNITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token::state::{Account as TokenAccount, AccountState};
+
+    fn token_data(owner: Pubkey, mint: Pubkey, amount: u64, state: AccountState) -> Vec<u8> {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount { mint, owner, amount, state, ..TokenAccount::default() },
+            &mut data,
+        )
+        .unwrap();
+        data
+    }
+
+    fn verify(owner: Pubkey, mint: Pubkey, program: Pubkey, mut data: Vec<u8>) -> Result<bool> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &program, false, 0);
+        verify_nft_ownership(owner, mint, &account)
+    }
+
+    #[test]
+    fn accepts_an_owned_unfrozen_token() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = token_data(owner, mint, 1, AccountState::Initialized);
+        assert!(verify(owner, mint, spl_token::ID, data).unwrap());
+    }
+
+    #[test]
+    fn rejects_another_owner() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = token_data(Pubkey::new_unique(), mint, 1, AccountState::Initialized);
+        assert!(!verify(owner, mint, spl_token::ID, data).unwrap());
+    }
+
+    #[test]
+    fn rejects_another_mint() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = token_data(owner, Pubkey::new_unique(), 1, AccountState::Initialized);
+        assert!(!verify(owner, mint, spl_token::ID, data).unwrap());
+    }
+
+    #[test]
+    fn rejects_an_empty_account() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = token_data(owner, mint, 0, AccountState::Initialized);
+        assert!(!verify(owner, mint, spl_token::ID, data).unwrap());
+    }
+
+    #[test]
+    fn rejects_a_frozen_account() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = token_data(owner, mint, 1, AccountState::Frozen);
+        assert!(!verify(owner, mint, spl_token::ID, data).unwrap());
+    }
+
+    #[test]
+    fn rejects_data_not_owned_by_the_token_program() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = token_data(owner, mint, 1, AccountState::Initialized);
+        assert!(!verify(owner, mint, Pubkey::new_unique(), data).unwrap());
+    }
+
+    #[test]
+    fn resign_payout_is_conserved() {
+        for wager in [0, 1, 7, 1_000_000_007, u64::MAX / 2] {
+            for penalty_bps in [0, 1, 5_000, 9_999, 10_000] {
+                for fee_bps in [0, 1, 250, 10_000] {
+                    let (winner, resigner, platform) = compute_resign_payout(wager, penalty_bps, fee_bps);
+                    assert_eq!(winner as u128 + resigner as u128 + platform as u128, wager as u128 * 2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resign_payout_splits_after_fees() {
+        assert_eq!(compute_resign_payout(1_000, 10_000, 0), (2_000, 0, 0));
+        assert_eq!(compute_resign_payout(1_000, 7_500, 0), (1_500, 500, 0));
+        assert_eq!(compute_resign_payout(1_000, 10_000, 500), (1_900, 0, 100));
+    }
+
+    #[test]
+    fn prize_distribution_adds_up_to_the_pool() {
+        let amounts = compute_prize_distribution(1_000_001, &[5_000, 3_000, 2_000]).unwrap();
+        assert_eq!(amounts, vec![500_001, 300_000, 200_000]);
+        let amounts = compute_prize_distribution(u64::MAX, &[3_333, 3_333, 3_334]).unwrap();
+        assert_eq!(amounts.iter().map(|amount| *amount as u128).sum::<u128>(), u64::MAX as u128);
+    }
+
+    #[test]
+    fn prize_shares_have_to_sum_to_10000() {
+        assert!(compute_prize_distribution(100, &[5_000, 4_000]).is_err());
+        assert!(compute_prize_distribution(100, &[]).is_err());
+    }
+
+    #[test]
+    fn elo_changes_follow_the_expected_score() {
+        assert_eq!(compute_elo_change(1200, 1200, 10_000), 16);
+        assert_eq!(compute_elo_change(1200, 1200, 0), -16);
+        assert_eq!(compute_elo_change(1200, 1200, 5_000), 0);
+        assert_eq!(compute_elo_change(1600, 1200, 10_000), 3); // the favourite was expected to win
+        assert_eq!(compute_elo_change(2400, 1200, 10_000), 3); // capped at a 400 point difference
+        assert_eq!(compute_elo_change(1200, 1600, 10_000), 29);
+    }
+
+    #[test]
+    fn elo_changes_add_up_to_zero() {
+        for (a, b) in [(1200, 1200), (1213, 1187), (900, 2300), (1500, 1451)] {
+            for score_bps in [0, 5_000, 10_000] {
+                assert_eq!(compute_elo_change(a, b, score_bps) + compute_elo_change(b, a, 10_000 - score_bps), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn compute_estimate_adds_up_features() {
+        assert_eq!(estimate_compute_units(3, 0), 6_500);
+        assert_eq!(estimate_compute_units(3, HINT_FLAG | ELO_UPDATE_FLAG), 126_500);
+        assert_eq!(estimate_compute_units(3, HINT_FLAG | HISTORY_FLAG | ELO_UPDATE_FLAG), 176_500);
+        assert_eq!(estimate_compute_units(3, 1 << 7), 6_500);
+        // still inside the 1.4M units a transaction can request
+        assert!(estimate_compute_units(u8::MAX, u8::MAX) <= 1_400_000);
+    }
+
+    #[test]
+    fn search_estimate_bounds_the_expected_outcome() {
+        assert_eq!(estimate_search_compute_units(0), 2_000);
+        assert_eq!(estimate_search_compute_units(2), (1 + 2 + 2) * 2_000);
+        assert!(estimate_search_compute_units(MAX_SEARCH_FREE_CELLS) <= 1_400_000);
+        assert!(estimate_search_compute_units(MAX_SEARCH_FREE_CELLS + 1) > 1_400_000);
+        assert_eq!(estimate_search_compute_units(u8::MAX), u64::MAX); // saturates instead of wrapping
+    }
+}
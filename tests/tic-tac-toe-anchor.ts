@@ -1,7 +1,9 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
-import { expect } from "chai";
+import { AnchorError, Program } from "@coral-xyz/anchor";
+import chai, { expect } from "chai";
+import * as crypto from "crypto";
 import { TicTacToeAnchor } from "../target/types/tic_tac_toe_anchor";
+import { MockObserver } from "../target/types/mock_observer";
 
 describe("tic-tac-toe-anchor", () => {
   // Configure the client to use the local cluster.
@@ -9,16 +11,200 @@ describe("tic-tac-toe-anchor", () => {
 
   const program = anchor.workspace.TicTacToeAnchor as Program<TicTacToeAnchor>;
 
+  const [configPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+
+  const [statsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("stats")],
+    program.programId
+  );
+
+  const [treasuryPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury")],
+    program.programId
+  );
+
+  const [lobbyPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("lobby")],
+    program.programId
+  );
+
+  function rateLimitPda(instructionId: number) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("ratelimit"), Buffer.from([instructionId])],
+      program.programId
+    )[0];
+  }
+
+  function profilePda(player: anchor.web3.PublicKey) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("profile"), player.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  function vaultPda(game: anchor.web3.PublicKey) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), game.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  const noWager = new anchor.BN(0);
+
+  function banPda(player: anchor.web3.PublicKey) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("ban"), player.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  // the ban PDA of the signer has to be passed along with every state-mutating instruction
+  function banCheck(player: anchor.web3.PublicKey) {
+    return [{ pubkey: banPda(player), isWritable: false, isSigner: false }];
+  }
+
+  // update_config replaces all settings, so merge the changes into the current ones
+  async function updateSettings(changes: object) {
+    const config = await program.account.programConfig.fetch(configPda);
+    await program.methods
+      .updateConfig({ ...config.settings, ...changes })
+      .accounts({ config: configPda, authority: program.provider.publicKey })
+      .rpc();
+  }
+
+  async function airdrop(player: anchor.web3.PublicKey) {
+    const signature = await program.provider.connection.requestAirdrop(
+      player,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await program.provider.connection.confirmTransaction(signature);
+  }
+
+  // setup_game options with everything turned off, tests override what they need
+  function setupParams(changes: object = {}) {
+    return {
+      minimumEloRequirement: 0,
+      maximumEloRequirement: null,
+      referee: null,
+      initialTimeBankSecs: 0,
+      friendsOnly: false,
+      turnDeadlineSlots: new anchor.BN(0),
+      boardSize: 3,
+      winLength: 3,
+      ...changes,
+    };
+  }
+
+  // open games are set up without playerTwo, who can join them later
+  async function setupGame(referee: anchor.web3.PublicKey | null = null, open = false) {
+    const gameKeypair = anchor.web3.Keypair.generate();
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const playerTwo = anchor.web3.Keypair.generate();
+    const opponent = open ? anchor.web3.PublicKey.default : playerTwo.publicKey;
+    await program.methods
+      .setupGame(opponent, setupParams({ referee }), noWager)
+      .accounts({
+        game: gameKeypair.publicKey,
+        playerOne: playerOne.publicKey,
+        room: null,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(playerOne.publicKey),
+        vault: vaultPda(gameKeypair.publicKey),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .signers([gameKeypair])
+      .rpc();
+    return { gameKeypair, playerOne, playerTwo };
+  }
+
+  // plays the tiles in order, alternating between the provider wallet and playerTwo
+  async function playMoves(
+    game: anchor.web3.PublicKey,
+    playerTwo: anchor.web3.Keypair,
+    tiles: { row: number; column: number }[],
+    firstNonce = 0
+  ) {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    for (const [i, tile] of tiles.entries()) {
+      const player = i % 2 === 0 ? playerOne.publicKey : playerTwo.publicKey;
+      await program.methods
+        .play(tile, new anchor.BN(firstNonce + i))
+        .accounts({ game, player, config: configPda })
+        .remainingAccounts(banCheck(player))
+        .signers(i % 2 === 0 ? [] : [playerTwo])
+        .rpc();
+    }
+  }
+
+  // player one takes the top row
+  const playerOneWins = [
+    { row: 0, column: 0 },
+    { row: 1, column: 0 },
+    { row: 0, column: 1 },
+    { row: 1, column: 1 },
+    { row: 0, column: 2 },
+  ];
+
+  before(async () => {
+    const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeConfig()
+      .accounts({
+        config: configPda,
+        authority: program.provider.publicKey,
+        program: program.programId,
+        programData,
+      })
+      .rpc();
+    await program.methods
+      .initializeGlobalStats()
+      .accounts({ stats: statsPda, payer: program.provider.publicKey })
+      .rpc();
+    await program.methods
+      .initializeLobby()
+      .accounts({ lobby: lobbyPda, payer: program.provider.publicKey })
+      .rpc();
+    // analyze_threats and calculate_expected_outcome
+    for (const instructionId of [0, 1]) {
+      await program.methods
+        .initializeRateLimit(instructionId)
+        .accounts({
+          rateLimit: rateLimitPda(instructionId),
+          config: configPda,
+          authority: program.provider.publicKey,
+        })
+        .rpc();
+    }
+  });
+
   it("setup game!", async () => {
+    const statsBefore = await program.account.globalStats.fetch(statsPda);
     const gameKeypair = anchor.web3.Keypair.generate();
     const playerOne = (program.provider as anchor.AnchorProvider).wallet;
     const playerTwo = anchor.web3.Keypair.generate();
     await program.methods
-      .setupGame(playerTwo.publicKey)
+      .setupGame(playerTwo.publicKey, setupParams(), noWager)
       .accounts({
         game: gameKeypair.publicKey,
         playerOne: playerOne.publicKey,
+        room: null,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(playerOne.publicKey),
+        vault: vaultPda(gameKeypair.publicKey),
+        lobby: lobbyPda,
       })
+      .remainingAccounts(banCheck(playerOne.publicKey))
       .signers([gameKeypair])
       .rpc();
 
@@ -29,10 +215,2103 @@ describe("tic-tac-toe-anchor", () => {
       playerTwo.publicKey,
     ]);
     expect(gameState.state).to.eql({ active: {} });
-    expect(gameState.board).to.eql([
-      [null, null, null],
-      [null, null, null],
-      [null, null, null],
+    expect(gameState.board).to.eql(Array(25).fill(null));
+    expect([gameState.boardSize, gameState.winLength]).to.eql([3, 3]);
+    expect(gameState.createdVia).to.eql({ direct: {} });
+    const stats = await program.account.globalStats.fetch(statsPda);
+    expect(stats.gamesByOrigin[0].toNumber()).to.equal(
+      statsBefore.gamesByOrigin[0].toNumber() + 1
+    );
+  });
+
+  it("tracks the last played tile", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+
+    let gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.lastMoveTile).to.equal(null);
+
+    const signature = await program.methods
+      .play({ row: 0, column: 0 }, new anchor.BN(0))
+      .accounts({
+        game: gameKeypair.publicKey,
+        player: playerOne.publicKey,
+        config: configPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc({ commitment: "confirmed" });
+
+    gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.lastMoveTile).to.eql({ row: 0, column: 0 });
+    // the MovePlayed event carries the same tile
+    const tx = await program.provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+    });
+    const parser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+    const events = [...parser.parseLogs(tx.meta.logMessages)];
+    const movePlayed = events.find((event) => event.name === "MovePlayed");
+    expect(movePlayed.data.lastMoveTile).to.eql({ row: 0, column: 0 });
+    expect(movePlayed.data.turn).to.equal(1);
+
+    await program.methods
+      .play({ row: 1, column: 2 }, new anchor.BN(1))
+      .accounts({
+        game: gameKeypair.publicKey,
+        player: playerTwo.publicKey,
+        config: configPda,
+      })
+      .remainingAccounts(banCheck(playerTwo.publicKey))
+      .signers([playerTwo])
+      .rpc();
+
+    gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.lastMoveTile).to.eql({ row: 1, column: 2 });
+    expect(gameState.playCount).to.equal(2);
+    expect(gameState.moves.slice(0, 2)).to.eql([
+      { row: 0, column: 0, sign: { x: {} } },
+      { row: 1, column: 2, sign: { o: {} } },
+    ]);
+  });
+
+  it("only lets the current player move", async () => {
+    const { gameKeypair, playerTwo } = await setupGame();
+    try {
+      await program.methods
+        .play({ row: 0, column: 0 }, new anchor.BN(0))
+        .accounts({
+          game: gameKeypair.publicKey,
+          player: playerTwo.publicKey,
+          config: configPda,
+        })
+        .remainingAccounts(banCheck(playerTwo.publicKey))
+        .signers([playerTwo])
+        .rpc();
+      chai.assert(false, "should've failed because it's player one's turn");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("NotPlayersTurn");
+    }
+  });
+
+  it("rejects a replayed move with a stale nonce", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+
+    await program.methods
+      .play({ row: 0, column: 0 }, new anchor.BN(0))
+      .accounts({
+        game: gameKeypair.publicKey,
+        player: playerOne.publicKey,
+        config: configPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+
+    try {
+      await program.methods
+        .play({ row: 1, column: 1 }, new anchor.BN(0))
+        .accounts({
+          game: gameKeypair.publicKey,
+          player: playerTwo.publicKey,
+          config: configPda,
+        })
+        .remainingAccounts(banCheck(playerTwo.publicKey))
+        .signers([playerTwo])
+        .rpc();
+      chai.assert(false, "should've failed with a stale nonce");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("StaleNonce");
+    }
+
+    await program.methods
+      .play({ row: 1, column: 1 }, new anchor.BN(1))
+      .accounts({
+        game: gameKeypair.publicKey,
+        player: playerTwo.publicKey,
+        config: configPda,
+      })
+      .remainingAccounts(banCheck(playerTwo.publicKey))
+      .signers([playerTwo])
+      .rpc();
+
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.nonce.toNumber()).to.equal(2);
+  });
+
+  it("bans and reinstates a player", async () => {
+    const player = anchor.web3.Keypair.generate();
+    await airdrop(player.publicKey);
+    let gameKeypair = anchor.web3.Keypair.generate();
+    const setupAsPlayer = () =>
+      program.methods
+        .setupGame(
+          anchor.web3.Keypair.generate().publicKey,
+          setupParams(),
+          noWager
+        )
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: player.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: null,
+          profile: profilePda(player.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(player.publicKey))
+        .signers([gameKeypair, player])
+        .rpc();
+
+    await program.methods
+      .deactivatePlayer(player.publicKey, Array(32).fill(7))
+      .accounts({
+        bannedPlayer: banPda(player.publicKey),
+        config: configPda,
+        authority: program.provider.publicKey,
+      })
+      .rpc();
+
+    try {
+      await setupAsPlayer();
+      chai.assert(false, "should've failed because the player is banned");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("PlayerBanned");
+    }
+
+    await program.methods
+      .reinstatePlayer()
+      .accounts({
+        bannedPlayer: banPda(player.publicKey),
+        config: configPda,
+        authority: program.provider.publicKey,
+      })
+      .rpc();
+
+    gameKeypair = anchor.web3.Keypair.generate();
+    await setupAsPlayer();
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.players[0]).to.eql(player.publicKey);
+  });
+
+  it("sets up a game inside a room", async () => {
+    const creator = (program.provider as anchor.AnchorProvider).wallet;
+    const roomId = 0;
+    const [roomPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("room"), creator.publicKey.toBuffer(), Buffer.from([roomId])],
+      program.programId
+    );
+    const [memberPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("room_member"), roomPda.toBuffer(), creator.publicKey.toBuffer()],
+      program.programId
+    );
+    const name = Array.from(Buffer.from("classic".padEnd(32, "\0")));
+
+    await program.methods
+      .createRoom(
+        roomId,
+        name,
+        { boardSize: 3, winLength: 3, customRules: 0 },
+        0,
+        3000,
+        10
+      )
+      .accounts({ room: roomPda, creator: creator.publicKey })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .rpc();
+    await program.methods
+      .joinRoom()
+      .accounts({
+        room: roomPda,
+        member: memberPda,
+        player: creator.publicKey,
+        profile: profilePda(creator.publicKey),
+      })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .rpc();
+
+    const gameKeypair = anchor.web3.Keypair.generate();
+    await program.methods
+      .setupGame(
+        anchor.web3.Keypair.generate().publicKey,
+        setupParams(),
+        noWager
+      )
+      .accounts({
+        game: gameKeypair.publicKey,
+        playerOne: creator.publicKey,
+        room: roomPda,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(creator.publicKey),
+        vault: vaultPda(gameKeypair.publicKey),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .signers([gameKeypair])
+      .rpc();
+
+    // the creator has no profile and rates as 1200, below this room's floor
+    const [highRoomPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("room"), creator.publicKey.toBuffer(), Buffer.from([2])],
+      program.programId
+    );
+    await program.methods
+      .createRoom(
+        2,
+        name,
+        { boardSize: 3, winLength: 3, customRules: 0 },
+        1300,
+        3000,
+        0
+      )
+      .accounts({ room: highRoomPda, creator: creator.publicKey })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .rpc();
+    try {
+      await program.methods
+        .joinRoom()
+        .accounts({
+          room: highRoomPda,
+          member: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("room_member"),
+              highRoomPda.toBuffer(),
+              creator.publicKey.toBuffer(),
+            ],
+            program.programId
+          )[0],
+          player: creator.publicKey,
+          profile: profilePda(creator.publicKey),
+        })
+        .remainingAccounts(banCheck(creator.publicKey))
+        .rpc();
+      chai.assert(false, "should've failed because the rating is below the room's floor");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("RoomEloMismatch");
+    }
+
+    const room = await program.account.gameRoom.fetch(roomPda);
+    expect(room.memberCount).to.equal(1);
+    expect(room.activeGames).to.equal(1);
+    expect(room.minGamesBeforeRanking).to.equal(5);
+    const member = await program.account.roomMember.fetch(memberPda);
+    expect(member.calibrationRemaining).to.equal(5);
+    expect(member.unranked).to.be.true;
+
+    // the member just joined, so decay leaves them alone, and it can't run again this week
+    const decay = () =>
+      program.methods
+        .applyRatingDecay()
+        .accounts({ room: roomPda })
+        .remainingAccounts([
+          { pubkey: memberPda, isWritable: true, isSigner: false },
+        ])
+        .rpc();
+    await decay();
+    expect(
+      (await program.account.roomMember.fetch(memberPda)).eloRating
+    ).to.equal(1200);
+    try {
+      await decay();
+      chai.assert(false, "should've failed because decay already ran");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("DecayTooSoon");
+    }
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.room).to.eql(roomPda);
+  });
+
+  it("keeps score across games in a session", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const playerTwo = anchor.web3.Keypair.generate();
+    const [sessionPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("session"),
+        playerOne.publicKey.toBuffer(),
+        playerTwo.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .createSession({ eloDecider: {} })
+      .accounts({
+        session: sessionPda,
+        playerOne: playerOne.publicKey,
+        playerTwo: playerTwo.publicKey,
+        stats: statsPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+
+    for (const [i, tile] of playerOneWins.entries()) {
+      const player = i % 2 === 0 ? playerOne.publicKey : playerTwo.publicKey;
+      await program.methods
+        .playSession(tile, new anchor.BN(i))
+        .accounts({ session: sessionPda, player })
+        .remainingAccounts(banCheck(player))
+        .signers(i % 2 === 0 ? [] : [playerTwo])
+        .rpc();
+    }
+
+    await program.methods
+      .advanceSession()
+      .accounts({ session: sessionPda, player: playerOne.publicKey })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+
+    const session = await program.account.gameSession.fetch(sessionPda);
+    expect(session.gamesPlayed).to.equal(1);
+    expect(session.score).to.eql([1, 0]);
+    expect(session.gameState.turn).to.equal(1);
+    expect(session.gameState.state).to.eql({ active: {} });
+    expect(session.gameState.board).to.eql(Array(25).fill(null));
+
+    const finalizeSeries = () =>
+      program.methods
+        .finalizeSeries()
+        .accounts({
+          session: sessionPda,
+          player: playerOne.publicKey,
+          profileOne: profilePda(playerOne.publicKey),
+          profileTwo: profilePda(playerTwo.publicKey),
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .rpc();
+    // player one won the only game, no tiebreak needed
+    await finalizeSeries();
+    const decided = await program.account.gameSession.fetch(sessionPda);
+    expect(decided.winner).to.eql(playerOne.publicKey);
+    try {
+      await finalizeSeries();
+      chai.assert(false, "should've failed because the series is decided");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("SeriesAlreadyDecided");
+    }
+  });
+
+  it("expires open invites", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const joiner = anchor.web3.Keypair.generate();
+    const setOpenGame = async () => {
+      const gameKeypair = anchor.web3.Keypair.generate();
+      await program.methods
+        .setupGame(anchor.web3.PublicKey.default, setupParams(), noWager)
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: playerOne.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: null,
+          profile: profilePda(playerOne.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .signers([gameKeypair])
+        .rpc();
+      return gameKeypair.publicKey;
+    };
+    const join = (game: anchor.web3.PublicKey) =>
+      program.methods
+        .joinOpenGame(new anchor.BN(0))
+        .accounts({
+          game,
+          player: joiner.publicKey,
+          vault: vaultPda(game),
+          lobby: lobbyPda,
+          room: null,
+          profile: profilePda(joiner.publicKey),
+        })
+        .remainingAccounts(banCheck(joiner.publicKey))
+        .signers([joiner])
+        .rpc();
+    const setInviteTtl = (inviteTtlSecs: number) =>
+      updateSettings({ inviteTtlSecs: new anchor.BN(inviteTtlSecs) });
+
+    await setInviteTtl(2);
+    const joinedInTime = await setOpenGame();
+    const pendingGames = async () => {
+      const lobby = await program.account.gameLobby.fetch(lobbyPda);
+      return lobby.pendingGames.slice(0, lobby.count).map((game) => game.toBase58());
+    };
+    expect(await pendingGames()).to.include(joinedInTime.toBase58());
+    await join(joinedInTime);
+    const gameState = await program.account.game.fetch(joinedInTime);
+    expect(gameState.players[1]).to.eql(joiner.publicKey);
+    expect(await pendingGames()).to.not.include(joinedInTime.toBase58());
+
+    const expired = await setOpenGame();
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    try {
+      await join(expired);
+      chai.assert(false, "should've failed because the invite expired");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("InviteExpired");
+    }
+
+    await program.methods
+      .cancelGame()
+      .accounts({
+        game: expired,
+        playerOne: playerOne.publicKey,
+        referee: null,
+        vault: vaultPda(expired),
+        lobby: lobbyPda,
+        room: null,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+    expect(await program.provider.connection.getAccountInfo(expired)).to.equal(
+      null
+    );
+    expect(await pendingGames()).to.not.include(expired.toBase58());
+    await setInviteTtl(24 * 60 * 60);
+  });
+
+  it("waits for an opponent before the first move", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const gameKeypair = anchor.web3.Keypair.generate();
+    await program.methods
+      .setupGame(anchor.web3.PublicKey.default, setupParams(), noWager)
+      .accounts({
+        game: gameKeypair.publicKey,
+        playerOne: playerOne.publicKey,
+        room: null,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(playerOne.publicKey),
+        vault: vaultPda(gameKeypair.publicKey),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .signers([gameKeypair])
+      .rpc();
+
+    try {
+      await playMoves(gameKeypair.publicKey, anchor.web3.Keypair.generate(), [
+        { row: 1, column: 1 },
+      ]);
+      chai.assert(false, "should've failed because nobody joined yet");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("OpponentMissing");
+    }
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.lastMoveTile).to.equal(null);
+  });
+
+  it("stores a proof of play commitment", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    await playMoves(gameKeypair.publicKey, playerTwo, playerOneWins);
+
+    const secretNonce = crypto.randomBytes(32);
+    const commitment = Array.from(
+      crypto.createHash("sha256").update(secretNonce).digest()
+    );
+    const [proofPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("proof"), gameKeypair.publicKey.toBuffer(), Buffer.from([0])],
+      program.programId
+    );
+    const proveAsPlayerOne = () =>
+      program.methods
+        .proofOfPlay(commitment)
+        .accounts({
+          game: gameKeypair.publicKey,
+          proof: proofPda,
+          player: playerOne.publicKey,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .rpc();
+
+    await proveAsPlayerOne();
+    const proof = await program.account.playProof.fetch(proofPda);
+    expect(proof.role).to.equal(0);
+    expect(proof.game).to.eql(gameKeypair.publicKey);
+    // revealing the nonce off-chain proves the participation
+    expect(
+      Array.from(crypto.createHash("sha256").update(secretNonce).digest())
+    ).to.eql(proof.commitment);
+
+    try {
+      await proveAsPlayerOne();
+      chai.assert(false, "should've failed because the role already has a proof");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("AlreadyProved");
+    }
+  });
+
+  it("time-locks treasury withdrawals", async () => {
+    const authority = program.provider.publicKey;
+    const destination = anchor.web3.Keypair.generate().publicKey;
+    const [pendingPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("withdrawal")],
+      program.programId
+    );
+    await airdrop(treasuryPda);
+    await updateSettings({ withdrawalDelaySecs: new anchor.BN(2) });
+
+    const amount = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+    await program.methods
+      .initiateWithdrawal(amount, destination)
+      .accounts({
+        pendingWithdrawal: pendingPda,
+        config: configPda,
+        authority,
+      })
+      .rpc();
+    const execute = () =>
+      program.methods
+        .executeWithdrawal()
+        .accounts({
+          pendingWithdrawal: pendingPda,
+          treasury: treasuryPda,
+          destination,
+          config: configPda,
+          authority,
+        })
+        .rpc();
+
+    try {
+      await execute();
+      chai.assert(false, "should've failed because the withdrawal is locked");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("WithdrawalLocked");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await execute();
+    expect(
+      await program.provider.connection.getBalance(destination)
+    ).to.equal(amount.toNumber());
+    await updateSettings({ withdrawalDelaySecs: new anchor.BN(3 * 24 * 60 * 60) });
+  });
+
+  it("notifies registered observers once when the game is finalized", async () => {
+    const observerProgram = anchor.workspace
+      .MockObserver as Program<MockObserver>;
+    const counter = anchor.web3.Keypair.generate();
+    await observerProgram.methods
+      .initialize()
+      .accounts({
+        counter: counter.publicKey,
+        payer: program.provider.publicKey,
+      })
+      .signers([counter])
+      .rpc();
+
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    const [observerPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("observer"),
+        gameKeypair.publicKey.toBuffer(),
+        observerProgram.programId.toBuffer(),
+      ],
+      program.programId
+    );
+    const outsider = anchor.web3.Keypair.generate();
+    await airdrop(outsider.publicKey);
+    try {
+      await program.methods
+        .registerGameObserver(observerProgram.programId)
+        .accounts({
+          game: gameKeypair.publicKey,
+          observer: observerPda,
+          registeredBy: outsider.publicKey,
+        })
+        .remainingAccounts(banCheck(outsider.publicKey))
+        .signers([outsider])
+        .rpc();
+      chai.assert(false, "should've failed because only players register");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("NotAPlayer");
+    }
+    await program.methods
+      .registerGameObserver(observerProgram.programId)
+      .accounts({
+        game: gameKeypair.publicKey,
+        observer: observerPda,
+        registeredBy: playerOne.publicKey,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+    await playMoves(gameKeypair.publicKey, playerTwo, playerOneWins);
+
+    const finalize = () =>
+      program.methods
+        .finalizeGame()
+        .accounts({
+          game: gameKeypair.publicKey,
+          room: null,
+          memberOne: null,
+          memberTwo: null,
+        })
+        .remainingAccounts([
+          { pubkey: observerPda, isWritable: false, isSigner: false },
+          {
+            pubkey: observerProgram.programId,
+            isWritable: false,
+            isSigner: false,
+          },
+          { pubkey: counter.publicKey, isWritable: true, isSigner: false },
+        ])
+        .rpc();
+    await finalize();
+    try {
+      await finalize();
+      chai.assert(false, "should've failed because the game is finalized");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("GameAlreadyFinalized");
+    }
+
+    const counterState = await observerProgram.account.counter.fetch(
+      counter.publicKey
+    );
+    expect(counterState.calls.toNumber()).to.equal(1);
+    expect(counterState.lastGame).to.eql(gameKeypair.publicKey);
+    expect(counterState.lastWinner).to.eql(playerOne.publicKey);
+  });
+
+  it("enforces the ELO range when joining", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const joiner = anchor.web3.Keypair.generate();
+    // the joiner has no profile, so they count as a 1200 newcomer
+    const tryJoin = async (minimumElo: number, maximumElo: number | null) => {
+      const gameKeypair = anchor.web3.Keypair.generate();
+      await program.methods
+        .setupGame(
+          anchor.web3.PublicKey.default,
+          setupParams({
+            minimumEloRequirement: minimumElo,
+            maximumEloRequirement: maximumElo,
+          }),
+          noWager
+        )
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: playerOne.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: null,
+          profile: profilePda(playerOne.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .signers([gameKeypair])
+        .rpc();
+      await program.methods
+        .joinOpenGame(new anchor.BN(0))
+        .accounts({
+          game: gameKeypair.publicKey,
+          player: joiner.publicKey,
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+          room: null,
+          profile: profilePda(joiner.publicKey),
+        })
+        .remainingAccounts(banCheck(joiner.publicKey))
+        .signers([joiner])
+        .rpc();
+    };
+    const expectError = async (join: Promise<void>, code: string) => {
+      try {
+        await join;
+        chai.assert(false, `should've failed with ${code}`);
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+
+    await tryJoin(1200, null);
+    await tryJoin(0, 1200);
+    await expectError(tryJoin(1201, null), "EloTooLow");
+    await expectError(tryJoin(0, 1199), "EloTooHigh");
+  });
+
+  it("lets player two swap signs after the first move", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    const swap = (nonce: number) =>
+      program.methods
+        .swapSigns(new anchor.BN(nonce))
+        .accounts({ game: gameKeypair.publicKey, player: playerTwo.publicKey })
+        .remainingAccounts(banCheck(playerTwo.publicKey))
+        .signers([playerTwo])
+        .rpc();
+
+    await playMoves(gameKeypair.publicKey, playerTwo, [{ row: 1, column: 1 }]);
+    await swap(1);
+    let gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.players).to.eql([
+      playerTwo.publicKey,
+      playerOne.publicKey,
+    ]);
+    expect(gameState.swapApplied).to.equal(true);
+
+    // the opener now plays O
+    await program.methods
+      .play({ row: 0, column: 0 }, new anchor.BN(2))
+      .accounts({
+        game: gameKeypair.publicKey,
+        player: playerOne.publicKey,
+        config: configPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+    gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.board[0]).to.eql({ o: {} });
+
+    try {
+      await swap(3);
+      chai.assert(false, "should've failed because the swap window closed");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("SwapWindowClosed");
+    }
+  });
+
+  it("collects one survey per player after the game", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    await playMoves(gameKeypair.publicKey, playerTwo, playerOneWins);
+    const [surveyPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("survey"),
+        gameKeypair.publicKey.toBuffer(),
+        playerOne.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    const submit = (enjoyment: number) =>
+      program.methods
+        .submitSurvey(enjoyment, 4, true)
+        .accounts({
+          game: gameKeypair.publicKey,
+          survey: surveyPda,
+          stats: statsPda,
+          respondent: playerOne.publicKey,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .rpc();
+    const expectError = async (submission: Promise<string>, code: string) => {
+      try {
+        await submission;
+        chai.assert(false, `should've failed with ${code}`);
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+
+    await expectError(submit(5), "GameNotOver");
+    await program.methods
+      .finalizeGame()
+      .accounts({
+        game: gameKeypair.publicKey,
+        room: null,
+        memberOne: null,
+        memberTwo: null,
+      })
+      .rpc();
+    await expectError(submit(6), "InvalidSurveyScore");
+
+    const before = await program.account.globalStats.fetch(statsPda);
+    await submit(5);
+    const stats = await program.account.globalStats.fetch(statsPda);
+    expect(stats.surveyCount.toNumber()).to.equal(
+      before.surveyCount.toNumber() + 1
+    );
+    expect(stats.enjoymentSum.toNumber()).to.equal(
+      before.enjoymentSum.toNumber() + 5
+    );
+    const survey = await program.account.postGameSurvey.fetch(surveyPda);
+    expect(survey.fairness).to.equal(4);
+    expect(survey.wouldPlayAgain).to.equal(true);
+  });
+
+  it("lets only the referee resolve or call off a game", async () => {
+    const referee = anchor.web3.Keypair.generate();
+    const impostor = anchor.web3.Keypair.generate();
+    const resolve = (signer: anchor.web3.Keypair, game: anchor.web3.PublicKey) =>
+      program.methods
+        .resolveDisputedGame(null, new anchor.BN(0))
+        .accounts({ game, referee: signer.publicKey })
+        .remainingAccounts(banCheck(signer.publicKey))
+        .signers([signer])
+        .rpc();
+    const expectError = async (action: Promise<string>, code: string) => {
+      try {
+        await action;
+        chai.assert(false, `should've failed with ${code}`);
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+
+    const unrefereed = await setupGame();
+    await expectError(
+      resolve(referee, unrefereed.gameKeypair.publicKey),
+      "RefereeNotSet"
+    );
+
+    const disputed = await setupGame(referee.publicKey);
+    await expectError(
+      resolve(impostor, disputed.gameKeypair.publicKey),
+      "NotReferee"
+    );
+    await resolve(referee, disputed.gameKeypair.publicKey);
+    const gameState = await program.account.game.fetch(
+      disputed.gameKeypair.publicKey
+    );
+    expect(gameState.state).to.eql({ tie: {} });
+    // the verdict is final, a finished game can't be ruled on again
+    await expectError(
+      resolve(referee, disputed.gameKeypair.publicKey),
+      "GameAlreadyOver"
+    );
+
+    const open = await setupGame(referee.publicKey, true);
+    await expectError(resolve(referee, open.gameKeypair.publicKey), "OpponentMissing");
+
+    // a started game nobody agreed to cancel
+    const { gameKeypair, playerOne, playerTwo } = await setupGame(
+      referee.publicKey
+    );
+    await playMoves(gameKeypair.publicKey, playerTwo, [{ row: 1, column: 1 }]);
+    await program.methods
+      .cancelGame()
+      .accounts({
+        game: gameKeypair.publicKey,
+        playerOne: playerOne.publicKey,
+        referee: referee.publicKey,
+        vault: vaultPda(gameKeypair.publicKey),
+        lobby: lobbyPda,
+        room: null,
+      })
+      .remainingAccounts(banCheck(referee.publicKey))
+      .signers([referee])
+      .rpc();
+    expect(
+      await program.provider.connection.getAccountInfo(gameKeypair.publicKey)
+    ).to.equal(null);
+  });
+
+  it("keeps a friends list and gates friends-only games", async () => {
+    const owner = (program.provider as anchor.AnchorProvider).wallet;
+    const friend = anchor.web3.Keypair.generate();
+    const stranger = anchor.web3.Keypair.generate();
+    const [friendsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("friends"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    const setupWith = (playerTwo: anchor.web3.PublicKey) => {
+      const gameKeypair = anchor.web3.Keypair.generate();
+      return program.methods
+        .setupGame(playerTwo, setupParams({ friendsOnly: true }), noWager)
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: owner.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: friendsPda,
+          profile: profilePda(owner.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(owner.publicKey))
+        .signers([gameKeypair])
+        .rpc();
+    };
+    const expectNotFriends = async (action: Promise<string>) => {
+      try {
+        await action;
+        chai.assert(false, "should've failed with NotFriends");
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal("NotFriends");
+      }
+    };
+
+    const addFriend = () =>
+      program.methods
+        .addFriend(friend.publicKey)
+        .accounts({
+          friends: friendsPda,
+          owner: owner.publicKey,
+          friendProfile: profilePda(friend.publicKey),
+        })
+        .remainingAccounts(banCheck(owner.publicKey))
+        .rpc();
+    try {
+      await addFriend();
+      chai.assert(false, "should've failed because the friend has no profile");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("AccountNotInitialized");
+    }
+    await airdrop(friend.publicKey);
+    await program.methods
+      .createProfile()
+      .accounts({ profile: profilePda(friend.publicKey), player: friend.publicKey })
+      .remainingAccounts(banCheck(friend.publicKey))
+      .signers([friend])
+      .rpc();
+    await addFriend();
+    let list = await program.account.friendsList.fetch(friendsPda);
+    expect(list.friends[0]).to.eql(friend.publicKey);
+
+    await setupWith(friend.publicKey);
+    await expectNotFriends(setupWith(stranger.publicKey));
+
+    await program.methods
+      .removeFriend(friend.publicKey)
+      .accounts({ friends: friendsPda, owner: owner.publicKey })
+      .remainingAccounts(banCheck(owner.publicKey))
+      .rpc();
+    list = await program.account.friendsList.fetch(friendsPda);
+    expect(list.friends[0]).to.eql(anchor.web3.PublicKey.default);
+    await expectNotFriends(setupWith(friend.publicKey));
+  });
+
+  it("garbage collects abandoned games", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    const finder = anchor.web3.Keypair.generate();
+    await airdrop(treasuryPda);
+    const collect = () =>
+      program.methods
+        .garbageCollectGame()
+        .accounts({
+          game: gameKeypair.publicKey,
+          room: null,
+          config: configPda,
+          treasury: treasuryPda,
+          lobby: lobbyPda,
+          finder: finder.publicKey,
+          vault: vaultPda(gameKeypair.publicKey),
+          playerOne: playerOne.publicKey,
+          playerTwo: playerTwo.publicKey,
+        })
+        .remainingAccounts(banCheck(finder.publicKey))
+        .signers([finder])
+        .rpc();
+
+    try {
+      await collect();
+      chai.assert(false, "should've failed because the grace period is running");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("GameNotAbandoned");
+    }
+
+    await updateSettings({ gcGracePeriodSecs: new anchor.BN(1) });
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    const connection = program.provider.connection;
+    const rent = await connection.getBalance(gameKeypair.publicKey);
+    const treasuryBefore = await connection.getBalance(treasuryPda);
+    await collect();
+
+    expect(await connection.getAccountInfo(gameKeypair.publicKey)).to.equal(
+      null
+    );
+    const treasuryShare = Math.floor(rent / 100);
+    expect(await connection.getBalance(treasuryPda)).to.equal(
+      treasuryBefore + treasuryShare
+    );
+    expect(await connection.getBalance(finder.publicKey)).to.equal(
+      rent - treasuryShare
+    );
+    await updateSettings({ gcGracePeriodSecs: new anchor.BN(30 * 24 * 60 * 60) });
+  });
+
+  it("splits the wager of an abandoned game between the players", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const joiner = anchor.web3.Keypair.generate();
+    const finder = anchor.web3.Keypair.generate();
+    await airdrop(joiner.publicKey);
+    const connection = program.provider.connection;
+    const wager = anchor.web3.LAMPORTS_PER_SOL / 100;
+
+    const gameKeypair = anchor.web3.Keypair.generate();
+    const game = gameKeypair.publicKey;
+    await program.methods
+      .setupGame(
+        anchor.web3.PublicKey.default,
+        setupParams(),
+        new anchor.BN(wager)
+      )
+      .accounts({
+        game,
+        playerOne: playerOne.publicKey,
+        room: null,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(playerOne.publicKey),
+        vault: vaultPda(game),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .signers([gameKeypair])
+      .rpc();
+    await program.methods
+      .joinOpenGame(new anchor.BN(0))
+      .accounts({
+        game,
+        player: joiner.publicKey,
+        vault: vaultPda(game),
+        lobby: lobbyPda,
+        room: null,
+        profile: profilePda(joiner.publicKey),
+      })
+      .remainingAccounts(banCheck(joiner.publicKey))
+      .signers([joiner])
+      .rpc();
+
+    await updateSettings({ gcGracePeriodSecs: new anchor.BN(1) });
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    const playerOneBalance = await connection.getBalance(playerOne.publicKey);
+    const joinerBalance = await connection.getBalance(joiner.publicKey);
+    const signature = await program.methods
+      .garbageCollectGame()
+      .accounts({
+        game,
+        room: null,
+        config: configPda,
+        treasury: treasuryPda,
+        lobby: lobbyPda,
+        finder: finder.publicKey,
+        vault: vaultPda(game),
+        playerOne: playerOne.publicKey,
+        playerTwo: joiner.publicKey,
+      })
+      .remainingAccounts(banCheck(finder.publicKey))
+      .signers([finder])
+      .rpc({ commitment: "confirmed" });
+    // the provider wallet is player one and pays the transaction fee
+    const { meta } = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+    });
+
+    expect(await connection.getAccountInfo(game)).to.equal(null);
+    expect(await connection.getBalance(vaultPda(game))).to.equal(0);
+    expect(await connection.getBalance(playerOne.publicKey)).to.equal(
+      playerOneBalance + wager - meta.fee
+    );
+    expect(await connection.getBalance(joiner.publicKey)).to.equal(
+      joinerBalance + wager
+    );
+    await updateSettings({ gcGracePeriodSecs: new anchor.BN(30 * 24 * 60 * 60) });
+  });
+
+  it("sets up games from invitations between friends", async () => {
+    const from = (program.provider as anchor.AnchorProvider).wallet;
+    const friend = anchor.web3.Keypair.generate();
+    const stranger = anchor.web3.Keypair.generate();
+    await airdrop(friend.publicKey);
+    const [friendsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("friends"), from.publicKey.toBuffer()],
+      program.programId
+    );
+    const invitePda = (to: anchor.web3.PublicKey) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("invite"), from.publicKey.toBuffer(), to.toBuffer()],
+        program.programId
+      )[0];
+    const invite = (to: anchor.web3.PublicKey) =>
+      program.methods
+        .inviteToGame(to, setupParams({ initialTimeBankSecs: 30 }))
+        .accounts({
+          invitation: invitePda(to),
+          from: from.publicKey,
+          friends: friendsPda,
+          config: configPda,
+        })
+        .remainingAccounts(banCheck(from.publicKey))
+        .rpc();
+    const accept = (gameKeypair: anchor.web3.Keypair) =>
+      program.methods
+        .acceptInvitation()
+        .accounts({
+          invitation: invitePda(friend.publicKey),
+          game: gameKeypair.publicKey,
+          from: from.publicKey,
+          to: friend.publicKey,
+          config: configPda,
+          stats: statsPda,
+        })
+        .remainingAccounts(banCheck(friend.publicKey))
+        .signers([friend, gameKeypair])
+        .rpc();
+    const expectError = async (action: Promise<string>, code: string) => {
+      try {
+        await action;
+        chai.assert(false, `should've failed with ${code}`);
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+
+    await program.methods
+      .createProfile()
+      .accounts({ profile: profilePda(friend.publicKey), player: friend.publicKey })
+      .remainingAccounts(banCheck(friend.publicKey))
+      .signers([friend])
+      .rpc();
+    await program.methods
+      .addFriend(friend.publicKey)
+      .accounts({
+        friends: friendsPda,
+        owner: from.publicKey,
+        friendProfile: profilePda(friend.publicKey),
+      })
+      .remainingAccounts(banCheck(from.publicKey))
+      .rpc();
+    await expectError(invite(stranger.publicKey), "NotFriends");
+
+    // declining hands the rent back and allows a new invitation
+    await invite(friend.publicKey);
+    await program.methods
+      .declineInvitation()
+      .accounts({
+        invitation: invitePda(friend.publicKey),
+        from: from.publicKey,
+        to: friend.publicKey,
+      })
+      .remainingAccounts(banCheck(friend.publicKey))
+      .signers([friend])
+      .rpc();
+    expect(
+      await program.provider.connection.getAccountInfo(
+        invitePda(friend.publicKey)
+      )
+    ).to.equal(null);
+
+    await invite(friend.publicKey);
+    const gameKeypair = anchor.web3.Keypair.generate();
+    await accept(gameKeypair);
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.players).to.eql([from.publicKey, friend.publicKey]);
+    expect(gameState.timeBankP2).to.equal(30);
+
+    await updateSettings({ inviteTtlSecs: new anchor.BN(0) });
+    await invite(friend.publicKey);
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    await expectError(
+      accept(anchor.web3.Keypair.generate()),
+      "InvitationExpired"
+    );
+    await updateSettings({ inviteTtlSecs: new anchor.BN(24 * 60 * 60) });
+  });
+
+  it("verifies the layout of game accounts", async () => {
+    const { gameKeypair } = await setupGame();
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.version).to.equal(1);
+
+    await program.methods
+      .verifyAccountLayout()
+      .accounts({ game: gameKeypair.publicKey })
+      .rpc();
+    try {
+      await program.methods
+        .verifyAccountLayout()
+        .accounts({ game: configPda })
+        .rpc();
+      chai.assert(false, "should've failed because the config isn't a game");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("AccountDiscriminatorMismatch");
+    }
+  });
+
+  it("features one active game per room", async () => {
+    const creator = (program.provider as anchor.AnchorProvider).wallet;
+    const roomId = 1;
+    const [roomPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("room"), creator.publicKey.toBuffer(), Buffer.from([roomId])],
+      program.programId
+    );
+    const name = Array.from(Buffer.from("featured".padEnd(32, "\0")));
+    await program.methods
+      .createRoom(
+        roomId,
+        name,
+        { boardSize: 3, winLength: 3, customRules: 0 },
+        0,
+        3000,
+        0
+      )
+      .accounts({ room: roomPda, creator: creator.publicKey })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .rpc();
+
+    const gameKeypair = anchor.web3.Keypair.generate();
+    const playerTwo = anchor.web3.Keypair.generate();
+    await program.methods
+      .setupGame(playerTwo.publicKey, setupParams(), noWager)
+      .accounts({
+        game: gameKeypair.publicKey,
+        playerOne: creator.publicKey,
+        room: roomPda,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(creator.publicKey),
+        vault: vaultPda(gameKeypair.publicKey),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .signers([gameKeypair])
+      .rpc();
+
+    // games outside the room can't be featured
+    const { gameKeypair: outside } = await setupGame();
+    try {
+      await program.methods
+        .setFeaturedGame()
+        .accounts({
+          room: roomPda,
+          game: outside.publicKey,
+          creator: creator.publicKey,
+        })
+        .remainingAccounts(banCheck(creator.publicKey))
+        .rpc();
+      chai.assert(false, "should've failed because the game isn't in the room");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("RoomAccountMismatch");
+    }
+
+    await program.methods
+      .setFeaturedGame()
+      .accounts({
+        room: roomPda,
+        game: gameKeypair.publicKey,
+        creator: creator.publicKey,
+      })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .rpc();
+    let room = await program.account.gameRoom.fetch(roomPda);
+    expect(room.featuredGame).to.eql(gameKeypair.publicKey);
+
+    await playMoves(gameKeypair.publicKey, playerTwo, playerOneWins);
+    await program.methods
+      .finalizeGame()
+      .accounts({
+        game: gameKeypair.publicKey,
+        room: roomPda,
+        memberOne: null,
+        memberTwo: null,
+      })
+      .rpc();
+    room = await program.account.gameRoom.fetch(roomPda);
+    expect(room.featuredGame).to.equal(null);
+
+    // a game the referee calls off leaves the room as well
+    const referee = anchor.web3.Keypair.generate();
+    const cancelled = anchor.web3.Keypair.generate();
+    await program.methods
+      .setupGame(
+        playerTwo.publicKey,
+        setupParams({ referee: referee.publicKey }),
+        noWager
+      )
+      .accounts({
+        game: cancelled.publicKey,
+        playerOne: creator.publicKey,
+        room: roomPda,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(creator.publicKey),
+        vault: vaultPda(cancelled.publicKey),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .signers([cancelled])
+      .rpc();
+    await program.methods
+      .setFeaturedGame()
+      .accounts({
+        room: roomPda,
+        game: cancelled.publicKey,
+        creator: creator.publicKey,
+      })
+      .remainingAccounts(banCheck(creator.publicKey))
+      .rpc();
+    const activeGames = (await program.account.gameRoom.fetch(roomPda))
+      .activeGames;
+    await program.methods
+      .cancelGame()
+      .accounts({
+        game: cancelled.publicKey,
+        playerOne: creator.publicKey,
+        referee: referee.publicKey,
+        vault: vaultPda(cancelled.publicKey),
+        lobby: lobbyPda,
+        room: roomPda,
+      })
+      .remainingAccounts(banCheck(referee.publicKey))
+      .signers([referee])
+      .rpc();
+    room = await program.account.gameRoom.fetch(roomPda);
+    expect(room.activeGames).to.equal(activeGames - 1);
+    expect(room.featuredGame).to.equal(null);
+  });
+
+  it("caps expensive instructions per slot", async () => {
+    const { gameKeypair } = await setupGame();
+    const expectedOutcome = () =>
+      program.methods
+        .calculateExpectedOutcome()
+        .accounts({
+          game: gameKeypair.publicKey,
+          rateLimit: rateLimitPda(1),
+          config: configPda,
+        })
+        .rpc();
+    await expectedOutcome();
+    const rateLimit = await program.account.instructionRateLimit.fetch(
+      rateLimitPda(1)
+    );
+    expect(rateLimit.callsThisSlot).to.equal(1);
+
+    await updateSettings({ maxCallsPerSlot: 0 });
+    try {
+      await expectedOutcome();
+      chai.assert(false, "should've failed because of the global rate limit");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("GlobalRateLimitExceeded");
+    }
+    await updateSettings({ maxCallsPerSlot: 20 });
+  });
+
+  it("lets either player resign", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    await playMoves(gameKeypair.publicKey, playerTwo, [{ row: 1, column: 1 }]);
+
+    const stranger = anchor.web3.Keypair.generate();
+    try {
+      await program.methods
+        .resign(new anchor.BN(1))
+        .accounts({ game: gameKeypair.publicKey, player: stranger.publicKey })
+        .remainingAccounts(banCheck(stranger.publicKey))
+        .signers([stranger])
+        .rpc();
+      chai.assert(false, "should've failed because the signer isn't playing");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("NotAPlayer");
+    }
+
+    // it's player two's turn, but player one can resign anyway
+    await program.methods
+      .resign(new anchor.BN(1))
+      .accounts({ game: gameKeypair.publicKey, player: playerOne.publicKey })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.state).to.eql({ won: { winner: playerTwo.publicKey } });
+  });
+
+  it("escrows wagers and pays out the winner", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const joiner = anchor.web3.Keypair.generate();
+    await airdrop(joiner.publicKey);
+    const connection = program.provider.connection;
+    const wager = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 100);
+
+    const setupWagerGame = async (
+      playerTwo: anchor.web3.PublicKey,
+      amount: anchor.BN
+    ) => {
+      const gameKeypair = anchor.web3.Keypair.generate();
+      await program.methods
+        .setupGame(playerTwo, setupParams(), amount)
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: playerOne.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: null,
+          profile: profilePda(playerOne.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .signers([gameKeypair])
+        .rpc();
+      return gameKeypair.publicKey;
+    };
+    const expectError = async (call: Promise<unknown>, code: string) => {
+      try {
+        await call;
+        chai.assert(false, `should've failed with ${code}`);
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+    await expectError(
+      setupWagerGame(joiner.publicKey, wager),
+      "WagerRequiresOpenGame"
+    );
+    await expectError(
+      setupWagerGame(anchor.web3.PublicKey.default, new anchor.BN(1)),
+      "WagerTooSmall"
+    );
+
+    const game = await setupWagerGame(anchor.web3.PublicKey.default, wager);
+    const joinerBalance = await connection.getBalance(joiner.publicKey);
+    await program.methods
+      .joinOpenGame(new anchor.BN(0))
+      .accounts({
+        game,
+        player: joiner.publicKey,
+        vault: vaultPda(game),
+        lobby: lobbyPda,
+        room: null,
+        profile: profilePda(joiner.publicKey),
+      })
+      .remainingAccounts(banCheck(joiner.publicKey))
+      .signers([joiner])
+      .rpc();
+    expect(await connection.getBalance(vaultPda(game))).to.equal(
+      wager.toNumber() * 2
+    );
+    expect(await connection.getBalance(joiner.publicKey)).to.equal(
+      joinerBalance - wager.toNumber()
+    );
+
+    await playMoves(game, joiner, playerOneWins, 1);
+    const settle = () =>
+      program.methods
+        .settle()
+        .accounts({
+          game,
+          vault: vaultPda(game),
+          playerOne: playerOne.publicKey,
+          playerTwo: joiner.publicKey,
+          treasury: treasuryPda,
+          config: configPda,
+        })
+        .rpc();
+    const treasuryBalance = await connection.getBalance(treasuryPda);
+    await settle();
+    expect(await connection.getBalance(vaultPda(game))).to.equal(0);
+    expect(await connection.getBalance(treasuryPda)).to.equal(treasuryBalance);
+    await expectError(settle(), "WagerAlreadySettled");
+  });
+
+  it("lets the waiting player claim a stalled game", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const playerTwo = anchor.web3.Keypair.generate();
+    const setupWithDeadline = async (turnDeadlineSlots: number) => {
+      const gameKeypair = anchor.web3.Keypair.generate();
+      await program.methods
+        .setupGame(
+          playerTwo.publicKey,
+          setupParams({ turnDeadlineSlots: new anchor.BN(turnDeadlineSlots) }),
+          noWager
+        )
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: playerOne.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: null,
+          profile: profilePda(playerOne.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .signers([gameKeypair])
+        .rpc();
+      await playMoves(gameKeypair.publicKey, playerTwo, [{ row: 1, column: 1 }]);
+      return gameKeypair.publicKey;
+    };
+    const claim = (game: anchor.web3.PublicKey) =>
+      program.methods
+        .claimTimeout(new anchor.BN(1))
+        .accounts({ game, player: playerOne.publicKey, config: configPda })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .rpc();
+
+    const patient = await setupWithDeadline(1_000_000);
+    try {
+      await claim(patient);
+      chai.assert(false, "should've failed because the deadline hasn't passed");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("TurnDeadlineNotExpired");
+    }
+
+    const stalled = await setupWithDeadline(1);
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    await claim(stalled);
+    const gameState = await program.account.game.fetch(stalled);
+    expect(gameState.state).to.eql({ won: { winner: playerOne.publicKey } });
+  });
+
+  it("records results on player profiles", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    await airdrop(playerTwo.publicKey);
+    await program.methods
+      .createProfile()
+      .accounts({
+        profile: profilePda(playerTwo.publicKey),
+        player: playerTwo.publicKey,
+      })
+      .remainingAccounts(banCheck(playerTwo.publicKey))
+      .signers([playerTwo])
+      .rpc();
+    await playMoves(gameKeypair.publicKey, playerTwo, playerOneWins);
+
+    // player one has no profile, so only player two's loss gets recorded, against a 1200 rating
+    const recordResult = () =>
+      program.methods
+        .recordResult()
+        .accounts({
+          game: gameKeypair.publicKey,
+          profileOne: profilePda(playerOne.publicKey),
+          profileTwo: profilePda(playerTwo.publicKey),
+          config: configPda,
+        })
+        .rpc();
+    await recordResult();
+    const profile = await program.account.playerProfile.fetch(
+      profilePda(playerTwo.publicKey)
+    );
+    expect(profile.owner).to.eql(playerTwo.publicKey);
+    expect([profile.wins, profile.losses, profile.draws]).to.eql([0, 1, 0]);
+    expect(profile.gamesPlayed).to.equal(1);
+    expect(profile.eloRating).to.equal(1184);
+    expect(profile.seasonalRank).to.eql({ bronze: {} });
+
+    try {
+      await recordResult();
+      chai.assert(false, "should've failed because the result is recorded");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("ResultAlreadyRecorded");
+    }
+
+    // the 1184 rating now keeps player two out of games for 1200 and up
+    const openGame = anchor.web3.Keypair.generate();
+    await program.methods
+      .setupGame(
+        anchor.web3.PublicKey.default,
+        setupParams({ minimumEloRequirement: 1200 }),
+        noWager
+      )
+      .accounts({
+        game: openGame.publicKey,
+        playerOne: playerOne.publicKey,
+        room: null,
+        config: configPda,
+        stats: statsPda,
+        friends: null,
+        profile: profilePda(playerOne.publicKey),
+        vault: vaultPda(openGame.publicKey),
+        lobby: lobbyPda,
+      })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .signers([openGame])
+      .rpc();
+    try {
+      await program.methods
+        .joinOpenGame(new anchor.BN(0))
+        .accounts({
+          game: openGame.publicKey,
+          player: playerTwo.publicKey,
+          vault: vaultPda(openGame.publicKey),
+          lobby: lobbyPda,
+          room: null,
+          profile: profilePda(playerTwo.publicKey),
+        })
+        .remainingAccounts(banCheck(playerTwo.publicKey))
+        .signers([playerTwo])
+        .rpc();
+      chai.assert(false, "should've failed because player two's rating dropped");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("EloTooLow");
+    }
+  });
+
+  it("ends the game in a draw both players agree on", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    await playMoves(gameKeypair.publicKey, playerTwo, [{ row: 1, column: 1 }]);
+
+    await program.methods
+      .offerDraw(new anchor.BN(1))
+      .accounts({ game: gameKeypair.publicKey, player: playerOne.publicKey })
+      .remainingAccounts(banCheck(playerOne.publicKey))
+      .rpc();
+    try {
+      await program.methods
+        .acceptDraw(new anchor.BN(2))
+        .accounts({ game: gameKeypair.publicKey, player: playerOne.publicKey })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .rpc();
+      chai.assert(false, "should've failed because player one offered the draw");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("OwnDrawOffer");
+    }
+
+    await program.methods
+      .acceptDraw(new anchor.BN(2))
+      .accounts({ game: gameKeypair.publicKey, player: playerTwo.publicKey })
+      .remainingAccounts(banCheck(playerTwo.publicKey))
+      .signers([playerTwo])
+      .rpc();
+    const gameState = await program.account.game.fetch(gameKeypair.publicKey);
+    expect(gameState.state).to.eql({ tie: {} });
+    expect(gameState.drawOfferedBy).to.equal(null);
+  });
+
+  it("plays on bigger boards", async () => {
+    const playerOne = (program.provider as anchor.AnchorProvider).wallet;
+    const playerTwo = anchor.web3.Keypair.generate();
+    const setupBoard = async (boardSize: number, winLength: number) => {
+      const gameKeypair = anchor.web3.Keypair.generate();
+      await program.methods
+        .setupGame(
+          playerTwo.publicKey,
+          setupParams({ boardSize, winLength }),
+          noWager
+        )
+        .accounts({
+          game: gameKeypair.publicKey,
+          playerOne: playerOne.publicKey,
+          room: null,
+          config: configPda,
+          stats: statsPda,
+          friends: null,
+          profile: profilePda(playerOne.publicKey),
+          vault: vaultPda(gameKeypair.publicKey),
+          lobby: lobbyPda,
+        })
+        .remainingAccounts(banCheck(playerOne.publicKey))
+        .signers([gameKeypair])
+        .rpc();
+      return gameKeypair.publicKey;
+    };
+
+    try {
+      await setupBoard(4, 5);
+      chai.assert(false, "should've failed because nobody can get 5 in a row on a 4x4 board");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("InvalidBoardConfig");
+    }
+
+    const game = await setupBoard(4, 4);
+    await playMoves(game, playerTwo, [{ row: 3, column: 3 }]);
+    const gameState = await program.account.game.fetch(game);
+    expect(gameState.board[15]).to.eql({ x: {} }); // row 3 * 4 + column 3
+  });
+
+  it("sets up a rematch with the loser moving first", async () => {
+    const { gameKeypair, playerOne, playerTwo } = await setupGame();
+    const rematch = (
+      oldGame: anchor.web3.PublicKey,
+      banChecks = [...banCheck(playerOne.publicKey), ...banCheck(playerTwo.publicKey)]
+    ) => {
+      const newGame = anchor.web3.Keypair.generate();
+      return program.methods
+        .rematch()
+        .accounts({
+          oldGame,
+          newGame: newGame.publicKey,
+          initiator: playerOne.publicKey,
+          opponent: null,
+          vault: vaultPda(newGame.publicKey),
+          config: configPda,
+          stats: statsPda,
+          profile: profilePda(playerOne.publicKey),
+        })
+        .remainingAccounts(banChecks)
+        .signers([newGame])
+        .rpc()
+        .then(() => newGame.publicKey);
+    };
+    const expectError = async (action: Promise<unknown>, code: string) => {
+      try {
+        await action;
+        chai.assert(false, `should've failed with ${code}`);
+      } catch (_err) {
+        expect(_err).to.be.instanceOf(AnchorError);
+        const err: AnchorError = _err;
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+
+    await expectError(rematch(gameKeypair.publicKey), "GameNotOver");
+
+    await playMoves(gameKeypair.publicKey, playerTwo, playerOneWins);
+    // the opponent is checked for a ban too
+    await expectError(
+      rematch(gameKeypair.publicKey, banCheck(playerOne.publicKey)),
+      "BanCheckAccountMissing"
+    );
+    const banAccounts = {
+      bannedPlayer: banPda(playerTwo.publicKey),
+      config: configPda,
+      authority: program.provider.publicKey,
+    };
+    await program.methods
+      .deactivatePlayer(playerTwo.publicKey, Array(32).fill(7))
+      .accounts(banAccounts)
+      .rpc();
+    await expectError(rematch(gameKeypair.publicKey), "PlayerBanned");
+    await program.methods.reinstatePlayer().accounts(banAccounts).rpc();
+
+    const newGame = await rematch(gameKeypair.publicKey);
+    const gameState = await program.account.game.fetch(newGame);
+    expect(gameState.players).to.eql([playerTwo.publicKey, playerOne.publicKey]);
+    expect(gameState.createdVia).to.eql({ rematch: {} });
+    expect(gameState.turn).to.equal(1);
+  });
+
+  it("runs a four player tournament", async () => {
+    const organizer = (program.provider as anchor.AnchorProvider).wallet;
+    const [secondSeed, thirdSeed, fourthSeed] = [0, 1, 2].map(() =>
+      anchor.web3.Keypair.generate()
+    );
+    const nonce = new anchor.BN(0);
+    const [tournamentPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tournament"),
+        organizer.publicKey.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const gamePda = (slot: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("tournament_game"), tournamentPda.toBuffer(), Buffer.from([slot])],
+        program.programId
+      )[0];
+    const asRemaining = (slots: number[]) =>
+      slots.map((slot) => ({ pubkey: gamePda(slot), isWritable: true, isSigner: false }));
+
+    await program.methods
+      .createTournament(nonce, 4, [6_000, 4_000], false)
+      .accounts({ tournament: tournamentPda, organizer: organizer.publicKey })
+      .remainingAccounts(banCheck(organizer.publicKey))
+      .rpc();
+    const register = (tournament: anchor.web3.PublicKey, player: anchor.web3.PublicKey) =>
+      program.methods
+        .registerPlayer()
+        .accounts({ tournament, player, profile: profilePda(player), config: configPda })
+        .remainingAccounts(banCheck(player));
+    await register(tournamentPda, organizer.publicKey).rpc();
+    for (const player of [secondSeed, thirdSeed, fourthSeed]) {
+      await register(tournamentPda, player.publicKey).signers([player]).rpc();
+    }
+
+    // a ranked tournament turns away everyone below Silver, newcomers rate 1200
+    const rankedNonce = new anchor.BN(1);
+    const [rankedPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tournament"),
+        organizer.publicKey.toBuffer(),
+        rankedNonce.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    await program.methods
+      .createTournament(rankedNonce, 4, [], true)
+      .accounts({ tournament: rankedPda, organizer: organizer.publicKey })
+      .remainingAccounts(banCheck(organizer.publicKey))
+      .rpc();
+    try {
+      await register(rankedPda, secondSeed.publicKey).signers([secondSeed]).rpc();
+      chai.assert(false, "should've failed because the player is below Silver");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("EloTooLowForRanked");
+    }
+    await program.methods
+      .startTournament()
+      .accounts({
+        tournament: tournamentPda,
+        organizer: organizer.publicKey,
+        config: configPda,
+        stats: statsPda,
+      })
+      .remainingAccounts([
+        ...banCheck(organizer.publicKey),
+        ...asRemaining([0, 1]),
+      ])
+      .rpc();
+
+    const advance = async (slot: number, nextRound: number[] = []) => {
+      const { players } = await program.account.game.fetch(gamePda(slot));
+      await program.methods
+        .advanceWinner()
+        .accounts({
+          tournament: tournamentPda,
+          game: gamePda(slot),
+          payer: organizer.publicKey,
+          config: configPda,
+          stats: statsPda,
+          profileOne: profilePda(players[0]),
+          profileTwo: profilePda(players[1]),
+        })
+        .remainingAccounts([
+          ...banCheck(organizer.publicKey),
+          ...asRemaining(nextRound),
+        ])
+        .rpc();
+    };
+
+    try {
+      await advance(0);
+      chai.assert(false, "should've failed because nobody has played yet");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("GameNotOver");
+    }
+
+    await playMoves(gamePda(0), secondSeed, playerOneWins);
+    for (const [i, tile] of playerOneWins.entries()) {
+      const player = i % 2 === 0 ? thirdSeed : fourthSeed;
+      await program.methods
+        .play(tile, new anchor.BN(i))
+        .accounts({ game: gamePda(1), player: player.publicKey, config: configPda })
+        .remainingAccounts(banCheck(player.publicKey))
+        .signers([player])
+        .rpc();
+    }
+    await advance(0);
+    await advance(1, [2]);
+    const finalGame = await program.account.game.fetch(gamePda(2));
+    expect(finalGame.players).to.eql([organizer.publicKey, thirdSeed.publicKey]);
+    expect(finalGame.createdVia).to.eql({ tournament: {} });
+
+    // both finalists get a profile, the final is counted on them
+    await program.methods
+      .createProfile()
+      .accounts({ profile: profilePda(organizer.publicKey), player: organizer.publicKey })
+      .remainingAccounts(banCheck(organizer.publicKey))
+      .rpc();
+    await airdrop(thirdSeed.publicKey);
+    await program.methods
+      .createProfile()
+      .accounts({ profile: profilePda(thirdSeed.publicKey), player: thirdSeed.publicKey })
+      .remainingAccounts(banCheck(thirdSeed.publicKey))
+      .signers([thirdSeed])
+      .rpc();
+    await playMoves(gamePda(2), thirdSeed, playerOneWins);
+    await advance(2);
+    const tournament = await program.account.tournament.fetch(tournamentPda);
+    expect(tournament.winner).to.eql(organizer.publicKey);
+    const champion = await program.account.playerProfile.fetch(profilePda(organizer.publicKey));
+    expect([champion.tournamentWins, champion.tournamentRunnerUp]).to.eql([1, 0]);
+    const runnerUp = await program.account.playerProfile.fetch(profilePda(thirdSeed.publicKey));
+    expect([runnerUp.tournamentWins, runnerUp.tournamentRunnerUp]).to.eql([0, 1]);
+
+    const verifyBracket = (slots: number[]) =>
+      program.methods
+        .verifyTournamentBracket()
+        .accounts({ tournament: tournamentPda })
+        .remainingAccounts(
+          slots.map((slot) => ({
+            pubkey: gamePda(slot),
+            isWritable: false,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+    await verifyBracket([0, 1, 2]);
+    try {
+      await verifyBracket([0, 1]);
+      chai.assert(false, "should've failed because the final is missing");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("InvalidTournamentGame");
+    }
+
+    // the prize pool is funded with a plain transfer and keeps its rent
+    const connection = program.provider.connection;
+    const [prizePoolPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("prize_pool"), tournamentPda.toBuffer()],
+      program.programId
+    );
+    const [distributionPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("prize_distribution"), tournamentPda.toBuffer()],
+      program.programId
+    );
+    const pool = anchor.web3.LAMPORTS_PER_SOL / 10;
+    await program.provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: organizer.publicKey,
+          toPubkey: prizePoolPda,
+          lamports: pool,
+        })
+      )
+    );
+    await program.methods
+      .computePrizeDistribution()
+      .accounts({
+        tournament: tournamentPda,
+        distribution: distributionPda,
+        prizePool: prizePoolPda,
+        payer: organizer.publicKey,
+      })
+      .remainingAccounts(banCheck(organizer.publicKey))
+      .rpc();
+    const total = pool - (await connection.getMinimumBalanceForRentExemption(0));
+    let distribution = await program.account.prizeDistribution.fetch(
+      distributionPda
+    );
+    expect(distribution.entries.map((entry) => entry.player)).to.eql([
+      organizer.publicKey,
+      thirdSeed.publicKey,
+      secondSeed.publicKey,
+      fourthSeed.publicKey,
     ]);
+    const runnerUpShare = Math.floor((total * 4_000) / 10_000);
+    expect(distribution.entries[1].amount.toNumber()).to.equal(runnerUpShare);
+    expect(distribution.entries[2].amount.toNumber()).to.equal(0);
+
+    const claim = () =>
+      program.methods
+        .claimPrize()
+        .accounts({
+          tournament: tournamentPda,
+          distribution: distributionPda,
+          prizePool: prizePoolPda,
+          player: thirdSeed.publicKey,
+        })
+        .remainingAccounts(banCheck(thirdSeed.publicKey))
+        .signers([thirdSeed])
+        .rpc();
+    const runnerUpBalance = await connection.getBalance(thirdSeed.publicKey);
+    await claim();
+    expect(await connection.getBalance(thirdSeed.publicKey)).to.equal(
+      runnerUpBalance + runnerUpShare
+    );
+    try {
+      await claim();
+      chai.assert(false, "should've failed because the prize was claimed");
+    } catch (_err) {
+      expect(_err).to.be.instanceOf(AnchorError);
+      const err: AnchorError = _err;
+      expect(err.error.errorCode.code).to.equal("PrizeAlreadyClaimed");
+    }
+    distribution = await program.account.prizeDistribution.fetch(
+      distributionPda
+    );
+    expect(distribution.entries[1].claimed).to.equal(true);
+  });
+
+  it("ranks solvers of the daily challenge", async () => {
+    const solver = anchor.web3.Keypair.generate();
+    await airdrop(solver.publicKey);
+    const date = Math.floor(Date.now() / 1000 / (24 * 60 * 60));
+    const dateBytes = Buffer.alloc(4);
+    dateBytes.writeUInt32LE(date);
+    const [leaderboardPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("daily_lb"), dateBytes],
+      program.programId
+    );
+    const [attemptsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("daily_attempts"),
+        dateBytes,
+        solver.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .createDailyLeaderboard(date)
+      .accounts({ leaderboard: leaderboardPda })
+      .rpc();
+
+    // the puzzle follows from the date, so just try every tile until one wins
+    let tries = 0;
+    let leaderboard = await program.account.dailyLeaderboard.fetch(
+      leaderboardPda
+    );
+    for (let cell = 0; cell < 9 && leaderboard.entries.length === 0; cell++) {
+      await program.methods
+        .submitDailySolution({ row: Math.floor(cell / 3), column: cell % 3 })
+        .accounts({
+          leaderboard: leaderboardPda,
+          attempts: attemptsPda,
+          solver: solver.publicKey,
+        })
+        .remainingAccounts(banCheck(solver.publicKey))
+        .signers([solver])
+        .rpc();
+      tries++;
+      leaderboard = await program.account.dailyLeaderboard.fetch(
+        leaderboardPda
+      );
+    }
+
+    expect(leaderboard.date).to.equal(date);
+    expect(leaderboard.entries.length).to.equal(1);
+    expect(leaderboard.entries[0].solver).to.eql(solver.publicKey);
+    expect(leaderboard.entries[0].attemptsUsed).to.equal(tries);
+    const attempts = await program.account.dailyAttempts.fetch(attemptsPda);
+    expect(attempts.attempts).to.equal(tries);
   });
 });